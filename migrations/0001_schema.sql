@@ -0,0 +1,66 @@
+-- the full schema the runner expects. written to also converge databases
+-- that were set up by hand before migrations existed.
+
+CREATE TABLE IF NOT EXISTS events (
+    id BIGSERIAL PRIMARY KEY,
+    series VARCHAR(8) NOT NULL,
+    year INTEGER NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    created TIMESTAMPTZ NOT NULL DEFAULT now(),
+    current SMALLINT NOT NULL DEFAULT 0,
+    new SMALLINT NOT NULL DEFAULT 1
+);
+
+-- concurrent runners insert events with ON CONFLICT against this.
+CREATE UNIQUE INDEX IF NOT EXISTS events_name_year_series
+    ON events (name, year, series);
+
+CREATE TABLE IF NOT EXISTS documents (
+    id BIGSERIAL PRIMARY KEY,
+    event BIGINT NOT NULL REFERENCES events (id),
+    series VARCHAR(8) NOT NULL,
+    title TEXT NOT NULL,
+    url TEXT NOT NULL,
+    mirror TEXT NOT NULL,
+    created TIMESTAMPTZ NOT NULL DEFAULT now(),
+    done SMALLINT NOT NULL DEFAULT 0
+);
+
+ALTER TABLE documents
+    ADD COLUMN IF NOT EXISTS published TIMESTAMPTZ NOT NULL DEFAULT now(),
+    ADD COLUMN IF NOT EXISTS notified BOOLEAN NOT NULL DEFAULT false,
+    -- sha256 of the pdf, detects documents re-published under a new url.
+    ADD COLUMN IF NOT EXISTS hash VARCHAR(64),
+    -- set once the FIA pulled the document from their site.
+    ADD COLUMN IF NOT EXISTS removed TIMESTAMPTZ,
+    -- null until the document got converted into page images.
+    ADD COLUMN IF NOT EXISTS page_count INTEGER,
+    ADD COLUMN IF NOT EXISTS doc_type VARCHAR(32) NOT NULL DEFAULT 'other',
+    ADD COLUMN IF NOT EXISTS source VARCHAR(16) NOT NULL DEFAULT 'fia.com';
+
+CREATE INDEX IF NOT EXISTS documents_event ON documents (event);
+CREATE INDEX IF NOT EXISTS documents_series_created
+    ON documents (series, created);
+CREATE INDEX IF NOT EXISTS documents_hash ON documents (hash);
+
+CREATE TABLE IF NOT EXISTS images (
+    id BIGSERIAL PRIMARY KEY,
+    document BIGINT NOT NULL REFERENCES documents (id) ON DELETE CASCADE,
+    url TEXT NOT NULL,
+    pagenum INTEGER NOT NULL,
+    created TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+-- sha256 of the page, shared by identical pages with content addressing.
+ALTER TABLE images ADD COLUMN IF NOT EXISTS hash VARCHAR(64);
+
+-- re-uploading a page replaces its row instead of duplicating it.
+CREATE UNIQUE INDEX IF NOT EXISTS images_document_pagenum
+    ON images (document, pagenum);
+CREATE INDEX IF NOT EXISTS images_hash ON images (hash);
+
+CREATE TABLE IF NOT EXISTS scan_state (
+    series VARCHAR(8) PRIMARY KEY,
+    last_scanned TIMESTAMPTZ NOT NULL,
+    last_document_count INTEGER NOT NULL
+);
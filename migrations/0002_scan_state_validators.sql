@@ -0,0 +1,4 @@
+-- cache validators of the season page, sent back to skip unchanged pages.
+ALTER TABLE scan_state
+    ADD COLUMN IF NOT EXISTS etag TEXT,
+    ADD COLUMN IF NOT EXISTS last_modified TEXT;
@@ -0,0 +1,2 @@
+-- perceptual hash of the page, only filled with the phash feature.
+ALTER TABLE images ADD COLUMN IF NOT EXISTS phash VARCHAR(32);
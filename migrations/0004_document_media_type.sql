@@ -0,0 +1,4 @@
+-- the FIA occasionally posts a scan instead of a pdf.
+ALTER TABLE documents
+    ADD COLUMN IF NOT EXISTS media_type VARCHAR(32) NOT NULL
+        DEFAULT 'application/pdf';
@@ -0,0 +1,3 @@
+-- parser that extracted a document's metadata, rows from before this are 0.
+ALTER TABLE documents
+    ADD COLUMN IF NOT EXISTS parser_version INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+-- the "Doc 34" of a title, numbered per event by the FIA.
+ALTER TABLE documents ADD COLUMN IF NOT EXISTS doc_number INTEGER;
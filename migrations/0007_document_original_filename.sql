@@ -0,0 +1,2 @@
+-- filename from the FIA's Content-Disposition, kept for provenance.
+ALTER TABLE documents ADD COLUMN IF NOT EXISTS original_filename TEXT;
@@ -0,0 +1,3 @@
+-- the document a revised one replaces, inferred from the titles.
+ALTER TABLE documents
+    ADD COLUMN IF NOT EXISTS supersedes BIGINT REFERENCES documents (id);
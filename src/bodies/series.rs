@@ -1,34 +1,86 @@
 use serde::{Deserialize, Serialize};
 
+use crate::model::series::Series as Kind;
+
 #[derive(Serialize, Deserialize)]
 pub struct Series {
-    kind: crate::model::series::Series,
+    kind: Kind,
     name: &'static str,
-    data_source: &'static str,
+    data_source: String,
 }
 
 impl Series {
-    pub fn f1() -> Self {
+    /// The documents page comes from `Series::docs_url`, so it follows the
+    /// configured season.
+    fn new(
+        kind: Kind,
+        name: &'static str,
+        year: i32,
+        season_id: u32,
+    ) -> Self {
         Self {
-            kind: crate::model::series::Series::f1,
-            name: "Formula 1",
-            data_source: "https://www.fia.com/documents/championships/fia-formula-one-world-championship-14/season/season-2023-2042"
+            kind,
+            name,
+            data_source: kind.docs_url(year, season_id),
         }
     }
 
-    pub fn f2() -> Self {
-        Self {
-            kind: crate::model::series::Series::f2,
-            name: "Formula 2",
-            data_source: "https://www.fia.com/documents/season/season-2023-2042/championships/formula-2-championship-44"
-        }
+    pub fn f1(
+        year: i32,
+        season_id: u32,
+    ) -> Self {
+        Self::new(Kind::f1, "Formula 1", year, season_id)
     }
 
-    pub fn f3() -> Self {
-        Self {
-            kind: crate::model::series::Series::f3,
-            name: "FIA Formula 3", 
-            data_source: "https://www.fia.com/documents/season/season-2023-2042/championships/fia-formula-3-championship-1012"
-        }
+    pub fn f2(
+        year: i32,
+        season_id: u32,
+    ) -> Self {
+        Self::new(Kind::f2, "Formula 2", year, season_id)
+    }
+
+    pub fn f3(
+        year: i32,
+        season_id: u32,
+    ) -> Self {
+        Self::new(Kind::f3, "FIA Formula 3", year, season_id)
+    }
+
+    pub fn wec(
+        year: i32,
+        season_id: u32,
+    ) -> Self {
+        Self::new(
+            Kind::wec,
+            "FIA World Endurance Championship",
+            year,
+            season_id,
+        )
+    }
+
+    pub fn wrc(
+        year: i32,
+        season_id: u32,
+    ) -> Self {
+        Self::new(Kind::wrc, "FIA World Rally Championship", year, season_id)
+    }
+
+    pub fn fe(
+        year: i32,
+        season_id: u32,
+    ) -> Self {
+        Self::new(
+            Kind::fe,
+            "ABB FIA Formula E World Championship",
+            year,
+            season_id,
+        )
+    }
+
+    pub fn f1a(
+        year: i32,
+        season_id: u32,
+    ) -> Self {
+        Self::new(Kind::f1a, "F1 Academy", year, season_id)
     }
 }
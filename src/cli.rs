@@ -0,0 +1,289 @@
+use sqlx::{types::chrono::Utc, Pool, Postgres};
+
+use crate::{
+    config::Config,
+    error::RunnerError,
+    middleware::{
+        fia::FiaClient,
+        magick::{check_magick, create_tmp_dir},
+        runner::{remirror, renotify},
+        storage,
+    },
+    model::{event::Event, series::Series},
+};
+
+const USAGE: &str = "usage: fia-docs-api [check | list-events --series <series> --year <year> | list-docs --event <id> | renotify --doc <id> | remirror --doc <id>]";
+// tables the migrations create, the runner needs all of them.
+const TABLES: [&str; 4] = ["events", "documents", "images", "scan_state"];
+
+/// Subcommands for checking what got ingested, and the odd manual fix.
+pub enum Command {
+    ListEvents {
+        series: Series,
+        year: i32,
+    },
+    ListDocs {
+        event: i64,
+    },
+    /// re-sends a document's discord notification.
+    Renotify {
+        doc: i64,
+    },
+    /// checks the configuration and every service the runner talks to.
+    Check,
+    /// downloads a document from the FIA again and replaces its mirror and
+    /// pages.
+    Remirror {
+        doc: i64,
+    },
+}
+
+impl Command {
+    /// Parses the arguments after the binary name, none means running the
+    /// scanner as usual.
+    pub fn parse(args: &[String]) -> Result<Option<Self>, String> {
+        let Some((command, flags)) = args.split_first() else {
+            return Ok(None);
+        };
+        let flag = |name: &str| -> Result<&str, String> {
+            flags
+                .iter()
+                .position(|arg| arg == name)
+                .and_then(|index| flags.get(index + 1))
+                .map(|value| value.as_str())
+                .ok_or_else(|| format!("missing {name}\n{USAGE}"))
+        };
+        return match command.as_str() {
+            "check" => Ok(Some(Self::Check)),
+            "list-events" => {
+                let series = flag("--series")?.parse::<Series>()?;
+                let year = flag("--year")?;
+                let year = year
+                    .parse::<i32>()
+                    .map_err(|_| format!("\"{year}\" is not a year"))?;
+                Ok(Some(Self::ListEvents {
+                    series,
+                    year,
+                }))
+            },
+            "list-docs" => {
+                let event = flag("--event")?;
+                let event = event
+                    .parse::<i64>()
+                    .map_err(|_| format!("\"{event}\" is not an event id"))?;
+                Ok(Some(Self::ListDocs {
+                    event,
+                }))
+            },
+            "renotify" => {
+                let doc = flag("--doc")?;
+                let doc = doc
+                    .parse::<i64>()
+                    .map_err(|_| format!("\"{doc}\" is not a document id"))?;
+                Ok(Some(Self::Renotify {
+                    doc,
+                }))
+            },
+            "remirror" => {
+                let doc = flag("--doc")?;
+                let doc = doc
+                    .parse::<i64>()
+                    .map_err(|_| format!("\"{doc}\" is not a document id"))?;
+                Ok(Some(Self::Remirror {
+                    doc,
+                }))
+            },
+            _ => Err(format!("unknown command \"{command}\"\n{USAGE}")),
+        };
+    }
+
+    /// Whether the command needs the full configuration, those run through
+    /// `run_configured` instead.
+    pub fn needs_config(&self) -> bool {
+        return matches!(self, Self::Check | Self::Remirror { .. });
+    }
+
+    pub async fn run(
+        self,
+        pool: &Pool<Postgres>,
+        client: &reqwest::Client,
+    ) -> Result<(), RunnerError> {
+        match self {
+            Self::ListEvents {
+                series,
+                year,
+            } => {
+                let series: String = series.into();
+                let events = sqlx::query_as_unchecked!(
+                    Event,
+                    "SELECT id as \"id?\", name, year, created, series as \"series: Series\" FROM events WHERE series = $1 AND year = $2 ORDER BY created",
+                    series,
+                    year
+                )
+                .fetch_all(pool)
+                .await?;
+                for event in events {
+                    println!(
+                        "{}\t{}\t{}",
+                        event.id.unwrap_or_default(),
+                        event.created.format("%Y-%m-%d"),
+                        event.name
+                    );
+                }
+            },
+            Self::ListDocs {
+                event,
+            } => {
+                struct Doc {
+                    id: i64,
+                    title: String,
+                    mirror: String,
+                    page_count: Option<i32>,
+                }
+                let docs = sqlx::query_as_unchecked!(
+                    Doc,
+                    "SELECT id, title, mirror, page_count FROM documents WHERE event = $1 ORDER BY published",
+                    event
+                )
+                .fetch_all(pool)
+                .await?;
+                for doc in docs {
+                    let pages = doc
+                        .page_count
+                        .map_or_else(|| "-".to_owned(), |c| c.to_string());
+                    println!(
+                        "{}\t{pages}\t{}\t{}",
+                        doc.id, doc.title, doc.mirror
+                    );
+                }
+            },
+            Self::Renotify {
+                doc,
+            } => {
+                let webhook =
+                    std::env::var("DISCORD_WEBHOOK_URL").map_err(|_| {
+                        RunnerError::Config(
+                            "DISCORD_WEBHOOK_URL is not set".to_owned(),
+                        )
+                    })?;
+                renotify(pool, client, &webhook, doc).await?;
+                println!("notified {doc}");
+            },
+            Self::Check
+            | Self::Remirror {
+                ..
+            } => {
+                return Err(RunnerError::Config(
+                    "command needs the full configuration".to_owned(),
+                ))
+            },
+        }
+        return Ok(());
+    }
+
+    /// Runs a command that needs the full configuration, returns whether
+    /// it succeeded.
+    pub async fn run_configured(
+        self,
+        config: &Config,
+        pool: &Pool<Postgres>,
+        client: &reqwest::Client,
+        fia: &FiaClient,
+    ) -> bool {
+        match self {
+            Self::Check => return check(config, pool, client, fia).await,
+            Self::Remirror {
+                doc,
+            } => {
+                if let Err(why) = remirror(pool, client, fia, config, doc).await
+                {
+                    println!("failed\tremirror {doc}: {why}");
+                    return false;
+                }
+                println!("remirrored {doc}");
+                return true;
+            },
+            // the rest go through `run`.
+            _ => return false,
+        }
+    }
+}
+
+/// Verifies imagemagick, the tmp dir, the database, fia.com and storage,
+/// printing a line per check. Returns whether all of them passed.
+pub async fn check(
+    config: &Config,
+    pool: &Pool<Postgres>,
+    client: &reqwest::Client,
+    fia: &FiaClient,
+) -> bool {
+    let mut passed = true;
+    let mut report = |name: &str, result: Result<(), String>| match result {
+        Ok(()) => println!("ok\t{name}"),
+        Err(why) => {
+            println!("failed\t{name}: {why}");
+            passed = false;
+        },
+    };
+
+    if config.mirror_only {
+        println!("skipped\timagemagick: mirror only");
+    } else {
+        report(
+            "imagemagick",
+            check_magick()
+                .then_some(())
+                .ok_or_else(|| "convert not found".to_owned()),
+        );
+    }
+
+    // a probe file instead of clearing, a running instance may use the dir.
+    let probe = config.tmp_dir.join(".check");
+    let tmp_dir = async {
+        create_tmp_dir(&config.tmp_dir)?;
+        tokio::fs::write(&probe, b"check").await?;
+        tokio::fs::remove_file(&probe).await
+    };
+    report("tmp dir", tmp_dir.await.map_err(|why| why.to_string()));
+
+    let mut missing = vec![];
+    let mut database = Ok(());
+    for table in TABLES {
+        match sqlx::query_scalar!(
+            r#"SELECT to_regclass($1) IS NOT NULL AS "exists!""#,
+            table
+        )
+        .fetch_one(pool)
+        .await
+        {
+            Ok(true) => {},
+            Ok(false) => missing.push(table),
+            Err(why) => {
+                database = Err(why.to_string());
+                break;
+            },
+        }
+    }
+    if database.is_ok() && !missing.is_empty() {
+        database = Err(format!("missing tables {}", missing.join(", ")));
+    }
+    report("database", database);
+
+    let url = config.docs_url(Series::f1);
+    let fia_result = match fia.get(&url).await {
+        Ok(response) if response.status().is_success() => Ok(()),
+        Ok(response) => Err(RunnerError::status(&response).to_string()),
+        Err(why) => Err(why.to_string()),
+    };
+    report("fia.com", fia_result);
+
+    let key = format!("check/{}.txt", Utc::now().timestamp_millis());
+    let storage = storage::open(client, &config.storage, Series::f1);
+    let stored = async {
+        storage.put(&key, b"check", "text/plain").await?;
+        storage.delete(&key).await
+    };
+    report("storage", stored.await.map_err(|why| why.to_string()));
+
+    return passed;
+}
@@ -0,0 +1,635 @@
+use std::{
+    collections::HashMap,
+    path::{Component, Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::{Datelike, Utc};
+
+use crate::{
+    error::RunnerError,
+    middleware::{
+        fia::FiaClient,
+        magick::{check_magick, ConvertOptions},
+        parser::fetch_season_id,
+    },
+    model::series::{Series, FIA_URL, SPORTITY_URL},
+};
+
+// matches the interval the runner has always used.
+const DEFAULT_SCAN_INTERVAL: u64 = 180;
+const DEFAULT_SCAN_JITTER_PERCENT: u8 = 20;
+const DEFAULT_FIA_MIN_REQUEST_INTERVAL: u64 = 500;
+const DEFAULT_REQUEST_TIMEOUT: u64 = 30;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const DEFAULT_USER_AGENT: &str =
+    concat!("fia-docs-api/", env!("CARGO_PKG_VERSION"));
+const DEFAULT_MAX_DOWNLOAD_BYTES: u64 = 50 * 1024 * 1024;
+const DEFAULT_MAX_CONCURRENT_DOCS: usize = 4;
+const DEFAULT_MAX_CONSECUTIVE_FAILURES: u32 = 3;
+// series and their documents are scanned concurrently, each holding a
+// connection while it queries.
+const DEFAULT_DB_POOL_SIZE: u32 = 10;
+const DEFAULT_TMP_DIR: &str = "./tmp";
+const DEFAULT_S3_REGION: &str = "us-east-1";
+const DEFAULT_S3_HOST: &str = "fia.ort.dev";
+const S3_CANNED_ACLS: [&str; 7] = [
+    "private",
+    "public-read",
+    "public-read-write",
+    "authenticated-read",
+    "aws-exec-read",
+    "bucket-owner-read",
+    "bucket-owner-full-control",
+];
+// the runner can't do anything useful without these.
+const REQUIRED_ENV: [&str; 1] = ["DATABASE_URL"];
+const REQUIRED_S3_ENV: [&str; 2] = ["S3_ACCESS_KEY", "S3_SECRET_KEY"];
+const DEFAULT_LOCAL_STORAGE_DIR: &str = "./storage";
+
+pub struct Config {
+    pub database_url: String,
+    /// connections shared by all runners.
+    pub db_pool_size: u32,
+    pub scan_interval: Duration,
+    /// fraction the wait between scans is randomly stretched or shrunk by,
+    /// so several instances don't hit the FIA in lockstep.
+    pub scan_jitter: f64,
+    /// the season to scrape documents for.
+    pub year: i32,
+    /// the FIA's internal id for `year`, part of every season path. none
+    /// until `resolve_season_id` looked up a season we don't know yet.
+    pub season_id: Option<u32>,
+    /// whether documents get converted into pages, needs imagemagick.
+    pub magick: bool,
+    /// only mirror the pdfs, skipping conversion even with imagemagick.
+    pub mirror_only: bool,
+    pub convert: ConvertOptions,
+    /// parse and log documents without writing to the database or bucket.
+    pub dry_run: bool,
+    /// flag documents that disappeared from the current event as removed.
+    pub reconcile_removed: bool,
+    /// minimum time between two requests to fia.com.
+    pub fia_min_interval: Duration,
+    /// where fia.com is reached, only changed to test against a mock.
+    pub fia_base_url: String,
+    /// where the Sportity channels of F1 Academy are reached.
+    pub sportity_base_url: String,
+    /// proxy requests to fia.com go through, on top of `HTTPS_PROXY`.
+    pub fia_proxy: Option<reqwest::Url>,
+    /// sent to fia.com so our requests are identifiable.
+    pub user_agent: String,
+    /// talk to the bucket directly even when a proxy is configured.
+    pub s3_no_proxy: bool,
+    /// upper bound for a whole request, including reading the body.
+    pub request_timeout: Duration,
+    pub connect_timeout: Duration,
+    /// port to serve health and metrics on, disabled when unset.
+    pub metrics_port: Option<u16>,
+    pub storage: StorageConfig,
+    /// scratch space for downloads and page images, cleared on startup.
+    pub tmp_dir: PathBuf,
+    /// exit after a single cycle, for cron jobs and smoke tests.
+    pub run_once: bool,
+    /// scanning a past season rather than the current one.
+    pub backfill: bool,
+    /// the series to scan, from a comma separated `SERIES`.
+    pub series: Vec<Series>,
+    /// re-render the pages of this event and exit.
+    pub reprocess_event: Option<i64>,
+    /// update the metadata of documents from an older parser and exit.
+    pub refresh_metadata: bool,
+    /// documents larger than this are skipped instead of downloaded.
+    pub max_download_bytes: u64,
+    /// new documents of a series mirrored at the same time.
+    pub max_concurrent_docs: usize,
+    /// failed cycles in a row before it's logged as an error, a single
+    /// failed one is usually a blip.
+    pub max_consecutive_failures: u32,
+}
+
+/// Where mirrors and page images get stored, shared by all backends.
+pub struct StorageConfig {
+    pub backend: StorageBackend,
+    /// base url files are served from, defaults to the endpoint.
+    pub public_url: String,
+    /// per series replacements for `public_url`, from `MIRROR_BASE_{SERIES}`.
+    pub series_public_urls: HashMap<Series, String>,
+    /// store pages under their hash, so identical pages share one object.
+    pub content_addressed_images: bool,
+    /// prepended to every key, e.g. `staging/` to keep test runs apart from
+    /// production. empty or ending in a slash.
+    pub prefix: String,
+}
+
+/// From `STORAGE_BACKEND`, a bucket unless set to `local`.
+pub enum StorageBackend {
+    S3(S3Config),
+    /// a directory, for local development without a bucket.
+    Local(PathBuf),
+}
+
+pub struct S3Config {
+    pub region: String,
+    /// host of the bucket endpoint, uploads go to `https://{host}/{key}`.
+    pub host: String,
+    /// set for path-style addressing, e.g. MinIO, uploads then go to
+    /// `https://{host}/{bucket}/{key}`.
+    pub path_style_bucket: Option<String>,
+    pub access_key: String,
+    pub secret_key: String,
+    /// canned acl uploads are stored with.
+    pub acl: String,
+}
+
+impl StorageConfig {
+    /// Expects `required_env` to have been checked.
+    fn from_env() -> Result<Self, String> {
+        let local = match std::env::var("STORAGE_BACKEND") {
+            Ok(backend) => match backend.to_lowercase().as_str() {
+                "s3" => false,
+                "local" => true,
+                _ => {
+                    return Err(format!(
+                        "STORAGE_BACKEND \"{backend}\" isn't s3 or local"
+                    ))
+                },
+            },
+            Err(_) => false,
+        };
+        let (backend, public_url) = match local {
+            true => {
+                let dir = std::env::var("LOCAL_STORAGE_DIR")
+                    .map(PathBuf::from)
+                    .unwrap_or_else(|_| {
+                        PathBuf::from(DEFAULT_LOCAL_STORAGE_DIR)
+                    });
+                let public_url = match std::env::var("LOCAL_STORAGE_URL") {
+                    Ok(url) => url,
+                    Err(_) => {
+                        let cwd = std::env::current_dir().map_err(|why| {
+                            format!("Couldn't resolve LOCAL_STORAGE_DIR: {why}")
+                        })?;
+                        format!("file://{}", cwd.join(&dir).display())
+                    },
+                };
+                (StorageBackend::Local(dir), public_url)
+            },
+            false => {
+                let s3 = S3Config::from_env()?;
+                let public_url = std::env::var("S3_PUBLIC_URL").unwrap_or_else(
+                    |_| match &s3.path_style_bucket {
+                        Some(bucket) => {
+                            format!("https://{}/{bucket}", s3.host)
+                        },
+                        None => format!("https://{}", s3.host),
+                    },
+                );
+                (StorageBackend::S3(s3), public_url)
+            },
+        };
+        let public_url = public_url.trim_end_matches('/').to_owned();
+        let series_public_urls = Series::all()
+            .iter()
+            .filter_map(|series| {
+                let name = format!(
+                    "MIRROR_BASE_{}",
+                    series.to_string().to_uppercase()
+                );
+                let url = std::env::var(name).ok()?;
+                Some((*series, url.trim_end_matches('/').to_owned()))
+            })
+            .collect();
+        let prefix = std::env::var("S3_PREFIX")
+            .map(|prefix| prefix.trim_matches('/').to_owned())
+            .ok()
+            .filter(|prefix| !prefix.is_empty())
+            .map_or_else(String::new, |prefix| format!("{prefix}/"));
+
+        return Ok(Self {
+            backend,
+            public_url,
+            series_public_urls,
+            content_addressed_images: env_flag("CONTENT_ADDRESSED_IMAGES"),
+            prefix,
+        });
+    }
+
+    /// Url `key` of `series` is publicly served from.
+    pub fn public(
+        &self,
+        series: Series,
+        key: &str,
+    ) -> String {
+        let base =
+            self.series_public_urls.get(&series).unwrap_or(&self.public_url);
+        return format!("{base}/{}{key}", self.prefix);
+    }
+}
+
+impl S3Config {
+    /// Expects `required_env` to have been checked.
+    fn from_env() -> Result<Self, String> {
+        let access_key = std::env::var("S3_ACCESS_KEY").unwrap_or_default();
+        let secret_key = std::env::var("S3_SECRET_KEY").unwrap_or_default();
+        let region = std::env::var("S3_REGION")
+            .unwrap_or_else(|_| DEFAULT_S3_REGION.to_owned());
+        let host = std::env::var("S3_HOST")
+            .unwrap_or_else(|_| DEFAULT_S3_HOST.to_owned());
+        let path_style_bucket = env_flag("S3_PATH_STYLE")
+            .then(|| std::env::var("S3_BUCKET").unwrap_or_default());
+        let acl = std::env::var("S3_ACL")
+            .unwrap_or_else(|_| "public-read".to_owned());
+        if !S3_CANNED_ACLS.contains(&acl.as_str()) {
+            return Err(format!(
+                "S3_ACL \"{acl}\" isn't one of {}",
+                S3_CANNED_ACLS.join(", ")
+            ));
+        }
+
+        return Ok(Self {
+            region,
+            host,
+            path_style_bucket,
+            access_key,
+            secret_key,
+            acl,
+        });
+    }
+
+    /// Url to upload `key` to.
+    pub fn endpoint(
+        &self,
+        key: &str,
+    ) -> String {
+        // the bucket is part of the signed path, the host stays the same.
+        return match &self.path_style_bucket {
+            Some(bucket) => format!("https://{}/{bucket}/{key}", self.host),
+            None => format!("https://{}/{key}", self.host),
+        };
+    }
+}
+
+impl Config {
+    pub fn from_env() -> Result<Self, String> {
+        // report everything that's missing at once instead of one per run.
+        let missing: Vec<&str> = required_env()
+            .into_iter()
+            .filter(|name| std::env::var(name).is_err())
+            .collect();
+        if !missing.is_empty() {
+            return Err(format!(
+                "missing required env vars: {}",
+                missing.join(", ")
+            ));
+        }
+        let database_url = std::env::var("DATABASE_URL").unwrap_or_default();
+
+        let scan_interval = std::env::var("SCAN_INTERVAL_SECS")
+            .ok()
+            .and_then(|secs| secs.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_SCAN_INTERVAL);
+
+        let scan_jitter = match std::env::var("SCAN_JITTER_PERCENT") {
+            Ok(percent) => percent
+                .parse::<u8>()
+                .ok()
+                .filter(|percent| *percent < 100)
+                .ok_or_else(|| {
+                    format!(
+                        "SCAN_JITTER_PERCENT \"{percent}\" is not between 0 and 99"
+                    )
+                })?,
+            Err(_) => DEFAULT_SCAN_JITTER_PERCENT,
+        };
+
+        let fia_min_interval =
+            match std::env::var("FIA_MIN_REQUEST_INTERVAL_MS") {
+                Ok(ms) => ms.parse::<u64>().map_err(|_| {
+                    format!(
+                        "FIA_MIN_REQUEST_INTERVAL_MS \"{ms}\" is not a number"
+                    )
+                })?,
+                Err(_) => DEFAULT_FIA_MIN_REQUEST_INTERVAL,
+            };
+
+        let fia_base_url = std::env::var("FIA_BASE_URL")
+            .map(|url| url.trim_end_matches('/').to_owned())
+            .unwrap_or_else(|_| FIA_URL.to_owned());
+        let sportity_base_url = std::env::var("SPORTITY_BASE_URL")
+            .map(|url| url.trim_end_matches('/').to_owned())
+            .unwrap_or_else(|_| SPORTITY_URL.to_owned());
+
+        let fia_proxy = match std::env::var("FIA_PROXY") {
+            Ok(proxy) => Some(reqwest::Url::parse(&proxy).map_err(|why| {
+                format!("FIA_PROXY \"{proxy}\" is not a url: {why}")
+            })?),
+            Err(_) => None,
+        };
+
+        let request_timeout = match std::env::var("REQUEST_TIMEOUT_SECS") {
+            Ok(secs) => secs
+                .parse::<u64>()
+                .ok()
+                .filter(|secs| *secs > 0)
+                .ok_or_else(|| {
+                    format!(
+                        "REQUEST_TIMEOUT_SECS \"{secs}\" is not a positive number"
+                    )
+                })?,
+            Err(_) => DEFAULT_REQUEST_TIMEOUT,
+        };
+        let request_timeout = Duration::from_secs(request_timeout);
+
+        let metrics_port = match std::env::var("METRICS_PORT") {
+            Ok(port) => Some(port.parse::<u16>().map_err(|_| {
+                format!("METRICS_PORT \"{port}\" is not a port")
+            })?),
+            Err(_) => None,
+        };
+
+        let tmp_dir = std::env::var("TMP_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| PathBuf::from(DEFAULT_TMP_DIR));
+        check_tmp_dir(&tmp_dir)?;
+
+        let max_download_bytes = match std::env::var("MAX_DOWNLOAD_BYTES") {
+            Ok(bytes) => bytes
+                .parse::<u64>()
+                .ok()
+                .filter(|bytes| *bytes > 0)
+                .ok_or_else(|| {
+                format!(
+                    "MAX_DOWNLOAD_BYTES \"{bytes}\" is not a positive number"
+                )
+            })?,
+            Err(_) => DEFAULT_MAX_DOWNLOAD_BYTES,
+        };
+
+        let max_concurrent_docs = match std::env::var("MAX_CONCURRENT_DOCS") {
+            Ok(docs) => docs
+                .parse::<usize>()
+                .ok()
+                .filter(|docs| *docs > 0)
+                .ok_or_else(|| {
+                    format!(
+                        "MAX_CONCURRENT_DOCS \"{docs}\" is not a positive number"
+                    )
+                })?,
+            Err(_) => DEFAULT_MAX_CONCURRENT_DOCS,
+        };
+
+        let db_pool_size = match std::env::var("DB_POOL_SIZE") {
+            Ok(size) => size
+                .parse::<u32>()
+                .ok()
+                .filter(|size| *size > 0)
+                .ok_or_else(|| {
+                    format!("DB_POOL_SIZE \"{size}\" is not a positive number")
+                })?,
+            Err(_) => DEFAULT_DB_POOL_SIZE,
+        };
+
+        let max_consecutive_failures =
+            match std::env::var("MAX_CONSECUTIVE_FAILURES") {
+                Ok(failures) => failures
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|failures| *failures > 0)
+                    .ok_or_else(|| {
+                        format!(
+                            "MAX_CONSECUTIVE_FAILURES \"{failures}\" is not a positive number"
+                        )
+                    })?,
+                Err(_) => DEFAULT_MAX_CONSECUTIVE_FAILURES,
+            };
+
+        // a backfill ingests a past season once and exits.
+        let backfill = match std::env::var("BACKFILL_YEAR") {
+            Ok(year) => Some(year.parse::<i32>().map_err(|_| {
+                format!("BACKFILL_YEAR \"{year}\" is not a year")
+            })?),
+            Err(_) => None,
+        };
+        let series = match std::env::var("SERIES") {
+            Ok(series) => series
+                .split(',')
+                .filter(|series| !series.trim().is_empty())
+                .map(|series| series.parse::<Series>())
+                .collect::<Result<Vec<_>, _>>()?,
+            Err(_) => Series::all().to_vec(),
+        };
+        if series.is_empty() {
+            return Err("SERIES doesn't name any series".to_owned());
+        }
+
+        let reprocess_event = match std::env::var("REPROCESS_EVENT") {
+            Ok(id) => Some(id.parse::<i64>().map_err(|_| {
+                format!("REPROCESS_EVENT \"{id}\" is not an event id")
+            })?),
+            Err(_) => None,
+        };
+
+        let year = match (backfill, std::env::var("SEASON_YEAR")) {
+            (Some(year), _) => year,
+            (None, Ok(year)) => year
+                .parse::<i32>()
+                .map_err(|_| format!("SEASON_YEAR \"{year}\" is not a year"))?,
+            (None, Err(_)) => Utc::now().year(),
+        };
+
+        let season_id =
+            match std::env::var("FIA_SEASON_ID") {
+                Ok(id) => Some(id.parse::<u32>().map_err(|_| {
+                    format!("FIA_SEASON_ID \"{id}\" is not an id")
+                })?),
+                Err(_) => fia_season_id(year),
+            };
+
+        let mirror_only = env_flag("MIRROR_ONLY");
+
+        let mut convert = ConvertOptions::default();
+        if let Ok(density) = std::env::var("MAGICK_DENSITY") {
+            convert.density = density
+                .parse::<u32>()
+                .ok()
+                .filter(|density| *density > 0)
+                .ok_or_else(|| {
+                    format!(
+                        "MAGICK_DENSITY \"{density}\" is not a positive number"
+                    )
+                })?;
+        }
+        if let Ok(quality) = std::env::var("MAGICK_QUALITY") {
+            convert.quality = quality
+                .parse::<u8>()
+                .ok()
+                .filter(|quality| (1..=100).contains(quality))
+                .ok_or_else(|| {
+                    format!(
+                        "MAGICK_QUALITY \"{quality}\" is not between 1 and 100"
+                    )
+                })?;
+        }
+        if let Ok(timeout) = std::env::var("MAGICK_TIMEOUT_SECS") {
+            let timeout = timeout
+                .parse::<u64>()
+                .ok()
+                .filter(|timeout| *timeout > 0)
+                .ok_or_else(|| {
+                    format!(
+                        "MAGICK_TIMEOUT_SECS \"{timeout}\" is not a positive number"
+                    )
+                })?;
+            convert.timeout = Duration::from_secs(timeout);
+        }
+        if let Ok(format) = std::env::var("IMAGE_FORMAT") {
+            convert.format = format.parse()?;
+        }
+        convert.drop_blank_pages = env_flag("DROP_BLANK_PAGES");
+
+        return Ok(Self {
+            database_url,
+            db_pool_size,
+            scan_interval: Duration::from_secs(scan_interval),
+            scan_jitter: f64::from(scan_jitter) / 100.0,
+            year,
+            season_id,
+            magick: !mirror_only && check_magick(),
+            mirror_only,
+            convert,
+            dry_run: env_flag("DRY_RUN"),
+            reconcile_removed: env_flag("RECONCILE_REMOVED"),
+            fia_min_interval: Duration::from_millis(fia_min_interval),
+            fia_base_url,
+            sportity_base_url,
+            fia_proxy,
+            user_agent: std::env::var("USER_AGENT")
+                .unwrap_or_else(|_| DEFAULT_USER_AGENT.to_owned()),
+            s3_no_proxy: env_flag("S3_NO_PROXY"),
+            request_timeout,
+            connect_timeout: CONNECT_TIMEOUT.min(request_timeout),
+            metrics_port,
+            storage: StorageConfig::from_env()?,
+            tmp_dir,
+            run_once: backfill.is_some() || env_flag("RUN_ONCE"),
+            backfill: backfill.is_some(),
+            series,
+            max_download_bytes,
+            max_concurrent_docs,
+            max_consecutive_failures,
+            reprocess_event,
+            refresh_metadata: env_flag("REFRESH_METADATA"),
+        });
+    }
+
+    /// Looks up the season id on fia.com if neither `FIA_SEASON_ID` nor
+    /// `fia_season_id` had it, e.g. for a season that just started.
+    pub async fn resolve_season_id(
+        &mut self,
+        fia: &FiaClient,
+    ) -> Result<u32, RunnerError> {
+        if let Some(season_id) = self.season_id {
+            return Ok(season_id);
+        }
+        let season_id =
+            fetch_season_id(fia, &self.fia_base_url, self.year).await?;
+        self.season_id = Some(season_id);
+        return Ok(season_id);
+    }
+
+    /// Url of a series' documents for the configured season.
+    pub fn docs_url(
+        &self,
+        series: Series,
+    ) -> String {
+        // only unresolved if startup skipped `resolve_season_id`, the page
+        // then comes up empty instead of scraping the wrong season.
+        let season_id = self.season_id.unwrap_or_default();
+        let base = match series {
+            Series::f1a => &self.sportity_base_url,
+            _ => &self.fia_base_url,
+        };
+        return format!("{base}{}", series.docs_path(self.year, season_id));
+    }
+}
+
+/// Every env var that has to be set, the S3 ones depend on the backend.
+fn required_env() -> Vec<&'static str> {
+    let mut required = REQUIRED_ENV.to_vec();
+    let local = std::env::var("STORAGE_BACKEND")
+        .is_ok_and(|backend| backend.eq_ignore_ascii_case("local"));
+    if !local {
+        required.extend(REQUIRED_S3_ENV);
+        if env_flag("S3_PATH_STYLE") {
+            required.push("S3_BUCKET");
+        }
+    }
+    return required;
+}
+
+/// The tmp dir gets wiped on startup, so it has to be a directory of its
+/// own, not `.`, `..`, `/` or anything the working directory is in.
+fn check_tmp_dir(tmp_dir: &Path) -> Result<(), String> {
+    let dedicated = || {
+        format!(
+            "TMP_DIR \"{}\" must be a dedicated directory",
+            tmp_dir.display()
+        )
+    };
+    if !matches!(tmp_dir.components().last(), Some(Component::Normal(_))) {
+        return Err(dedicated());
+    }
+    // a dir that doesn't exist yet can't hold anything else.
+    let Ok(canonical) = tmp_dir.canonicalize() else {
+        return Ok(());
+    };
+    let cwd =
+        std::env::current_dir().and_then(|cwd| cwd.canonicalize()).map_err(
+            |why| format!("Couldn't resolve the working directory: {why}"),
+        )?;
+    if canonical.parent().is_none() || cwd.starts_with(&canonical) {
+        return Err(dedicated());
+    }
+    return Ok(());
+}
+
+fn env_flag(name: &str) -> bool {
+    return std::env::var(name).is_ok_and(|value| {
+        matches!(value.to_lowercase().as_str(), "1" | "true" | "yes")
+    });
+}
+
+/// The FIA documents pages address seasons as `season-{year}-{id}`.
+pub fn fia_season_id(year: i32) -> Option<u32> {
+    return match year {
+        2022 => Some(2005),
+        2023 => Some(2042),
+        2024 => Some(2043),
+        2025 => Some(2071),
+        _ => None,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tmp_dir_has_to_be_dedicated() {
+        for dir in ["", ".", "..", "/", "./", "tmp/..", "/tmp/.."] {
+            assert!(check_tmp_dir(Path::new(dir)).is_err(), "{dir}");
+        }
+        let cwd = std::env::current_dir().unwrap();
+        assert!(check_tmp_dir(&cwd).is_err());
+        assert!(check_tmp_dir(cwd.parent().unwrap()).is_err());
+    }
+
+    #[test]
+    fn tmp_dir_can_be_new_or_elsewhere() {
+        assert!(check_tmp_dir(Path::new("./tmp")).is_ok());
+        assert!(check_tmp_dir(Path::new("/tmp")).is_ok());
+        let new = std::env::temp_dir().join("fia-docs-api-not-created");
+        assert!(check_tmp_dir(&new).is_ok());
+    }
+}
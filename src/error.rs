@@ -0,0 +1,57 @@
+use reqwest::header::{HeaderName, CONTENT_TYPE, RETRY_AFTER, SERVER};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum RunnerError {
+    #[error("download failed: {0}")]
+    Download(#[source] reqwest::Error),
+    /// the server answered, just not with what we asked for.
+    #[error("{status} from {url} (content-type: {content_type}, server: {server}, retry-after: {retry_after})")]
+    Status {
+        url: String,
+        status: reqwest::StatusCode,
+        content_type: String,
+        server: String,
+        retry_after: String,
+    },
+    #[error("upload failed: {0}")]
+    Upload(#[source] reqwest::Error),
+    #[error("notification failed: {0}")]
+    Notify(#[source] reqwest::Error),
+    #[error("database error: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("magick error: {0}")]
+    Magick(String),
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("unexpected content: {0}")]
+    Content(String),
+    #[error("parse error: {0}")]
+    Parse(String),
+    #[error("missing configuration: {0}")]
+    Config(String),
+    #[error("invalid header: {0}")]
+    Header(#[from] reqwest::header::InvalidHeaderValue),
+}
+
+impl RunnerError {
+    /// Keeps what a failed response told us, to tell blocks, missing
+    /// documents and login walls apart.
+    pub fn status(response: &reqwest::Response) -> Self {
+        let header = |name: HeaderName| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or("-")
+                .to_owned()
+        };
+        return Self::Status {
+            url: response.url().to_string(),
+            status: response.status(),
+            content_type: header(CONTENT_TYPE),
+            server: header(SERVER),
+            retry_after: header(RETRY_AFTER),
+        };
+    }
+}
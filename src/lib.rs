@@ -0,0 +1,12 @@
+pub mod bodies;
+pub mod cli;
+pub mod config;
+pub mod error;
+pub mod middleware;
+pub mod model;
+
+// the parser works without the database, bucket or imagemagick.
+pub use middleware::parser::{
+    get_season, parse_season, ParserDocument, ParserEvent, Season,
+};
+pub use model::series::Series;
@@ -1,38 +1,313 @@
-use middleware::magick::check_magick;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
 use sqlx::postgres::PgPoolOptions;
+use tracing::{error, info, warn};
+use tracing_subscriber::{
+    layer::SubscriberExt, util::SubscriberInitExt, EnvFilter,
+};
 
-use crate::middleware::{
-    magick::{clear_tmp_dir, create_tmp_dir},
-    runner::runner,
+use fia_docs_api::{
+    cli::Command,
+    config::Config,
+    middleware::{
+        fia::FiaClient,
+        magick::{clear_tmp_dir, create_tmp_dir},
+        metrics::serve_metrics,
+        runner::{refresh_metadata, reprocess_event, runner},
+    },
 };
-mod bodies;
-mod middleware;
-mod model;
+
+// distinct exit codes for fatal errors, so supervisors and alerts can tell
+// a broken deployment from a failed run.
+const EXIT_FAILURE: i32 = 1;
+const EXIT_CONFIG: i32 = 2;
+const EXIT_MAGICK: i32 = 3;
+const EXIT_DATABASE: i32 = 4;
+const EXIT_TMP_DIR: i32 = 5;
 
 #[tokio::main]
 async fn main() {
-    if !check_magick() {
-        eprintln!("Couldn't find imagemagick! exiting...");
-        std::process::exit(1);
+    drop(dotenvy::dotenv());
+
+    // json for log shippers, fields stay separate instead of in the message.
+    let json_logs = std::env::var("LOG_FORMAT").is_ok_and(|f| f == "json");
+    tracing_subscriber::registry()
+        .with(json_logs.then(|| tracing_subscriber::fmt::layer().json()))
+        .with((!json_logs).then(tracing_subscriber::fmt::layer))
+        .with(
+            EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| EnvFilter::new("info")),
+        )
+        .init();
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match Command::parse(&args) {
+        Ok(Some(command)) if command.needs_config() => {
+            run_configured(command).await;
+            return;
+        },
+        Ok(Some(command)) => {
+            run_command(command).await;
+            return;
+        },
+        Ok(None) => {},
+        Err(why) => {
+            error!("{why}");
+            std::process::exit(EXIT_CONFIG);
+        },
+    }
+
+    let mut config = match Config::from_env() {
+        Ok(config) => config,
+        Err(why) => {
+            error!("Invalid configuration: {why}");
+            std::process::exit(EXIT_CONFIG);
+        },
+    };
+
+    if let Err(why) = create_tmp_dir(&config.tmp_dir) {
+        error!("Couldn't create tmp dir: {why}");
+        std::process::exit(EXIT_TMP_DIR);
+    }
+    if let Err(why) = clear_tmp_dir(&config.tmp_dir) {
+        error!("Couldn't clear tmp dir: {why}");
+        std::process::exit(EXIT_TMP_DIR);
+    }
+
+    let database = match PgPoolOptions::new()
+        .max_connections(config.db_pool_size)
+        .connect_lazy(&config.database_url)
+    {
+        Ok(database) => database,
+        Err(why) => {
+            error!("Invalid DATABASE_URL: {why}");
+            std::process::exit(EXIT_DATABASE);
+        },
+    };
+    if config.dry_run {
+        warn!("Dry run, skipping database migrations.");
+    } else if let Err(why) = sqlx::migrate!().run(&database).await {
+        error!("Couldn't migrate the database: {why}");
+        std::process::exit(EXIT_DATABASE);
+    }
+    if config.mirror_only {
+        info!("Mirror only, documents won't get page images.");
+    } else if config.magick {
+        info!(
+            format = config.convert.format.extension(),
+            density = config.convert.density,
+            quality = config.convert.quality,
+            "Converting documents to page images"
+        );
+    } else {
+        warn!("Couldn't find imagemagick! documents won't get page images.");
     }
-    if let Err(why) = create_tmp_dir() {
-        eprintln!("Couldn't create tmp dir: {why}");
-        std::process::exit(1);
+    if config.dry_run {
+        warn!("Dry run, nothing will be written.");
     }
-    if let Err(why) = clear_tmp_dir() {
-        eprintln!("Couldn't create tmp dir: {why}");
-        std::process::exit(1);
+    if config.backfill {
+        info!(year = config.year, "Backfilling season");
+    } else {
+        info!(
+            year = config.year,
+            interval = config.scan_interval.as_secs(),
+            series = ?config.series,
+            "Scanning season"
+        );
     }
 
-    drop(dotenvy::dotenv());
-    let database_connect =
-        std::env::var("DATABASE_URL").expect("Database URL not set.");
+    if let Some(port) = config.metrics_port {
+        // a few missed cycles in a row means the runner is stuck.
+        let max_age = config.scan_interval * 3;
+        tokio::spawn(async move {
+            if let Err(why) = serve_metrics(port, max_age).await {
+                error!(port, "Metrics server failed: {why}");
+            }
+        });
+        info!(port, "Serving health and metrics");
+    }
 
-    let database = PgPoolOptions::new()
-        .connect_lazy(&database_connect)
-        .expect("Database Connection failed");
+    // shared so connections and TLS sessions get reused across requests.
+    let client = match http_client(&config, None, None, config.s3_no_proxy) {
+        Ok(client) => client,
+        Err(why) => {
+            error!("Couldn't build http client: {why}");
+            std::process::exit(EXIT_CONFIG);
+        },
+    };
+    let fia_client = match http_client(
+        &config,
+        config.fia_proxy.as_ref(),
+        Some(&config.user_agent),
+        false,
+    ) {
+        Ok(client) => client,
+        Err(why) => {
+            error!("Couldn't build FIA http client: {why}");
+            std::process::exit(EXIT_CONFIG);
+        },
+    };
+    let fia = FiaClient::new(fia_client, config.fia_min_interval);
+    match config.resolve_season_id(&fia).await {
+        Ok(season_id) => info!(year = config.year, season_id, "Season id"),
+        Err(why) => {
+            error!(
+                year = config.year,
+                "No known FIA season id, set FIA_SEASON_ID: {why}"
+            );
+            std::process::exit(EXIT_CONFIG);
+        },
+    }
 
-    drop(database_connect);
+    let stop = Arc::new(AtomicBool::new(false));
+    let signal = stop.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            warn!("Shutting down after the current document.");
+            signal.store(true, Ordering::Relaxed);
+        }
+    });
 
-    runner(&database).await;
+    if let Some(event_id) = config.reprocess_event {
+        if !config.magick {
+            error!("Reprocessing needs imagemagick.");
+            std::process::exit(EXIT_MAGICK);
+        }
+        let completed =
+            reprocess_event(&database, &client, &fia, &config, &stop, event_id)
+                .await;
+        database.close().await;
+        if !completed {
+            error!(event_id, "Reprocessing finished with errors");
+            std::process::exit(EXIT_FAILURE);
+        }
+        info!(event_id, "Reprocessing finished");
+        return;
+    }
+
+    if config.refresh_metadata {
+        let completed = refresh_metadata(&database, &fia, &config).await;
+        database.close().await;
+        if !completed {
+            error!("Refreshing metadata finished with errors");
+            std::process::exit(EXIT_FAILURE);
+        }
+        info!("Refreshing metadata finished");
+        return;
+    }
+
+    let completed = runner(&database, &client, &fia, &config, &stop).await;
+    database.close().await;
+    // an interrupted cycle isn't a failed one.
+    if !completed && !stop.load(Ordering::Relaxed) {
+        error!("Run finished with errors");
+        std::process::exit(EXIT_FAILURE);
+    }
+    info!("Run finished");
+}
+
+/// Runs a subcommand, which only needs the database.
+async fn run_command(command: Command) {
+    let Ok(database_url) = std::env::var("DATABASE_URL") else {
+        error!("DATABASE_URL is not set");
+        std::process::exit(EXIT_CONFIG);
+    };
+    let database = match PgPoolOptions::new().connect_lazy(&database_url) {
+        Ok(database) => database,
+        Err(why) => {
+            error!("Invalid DATABASE_URL: {why}");
+            std::process::exit(EXIT_DATABASE);
+        },
+    };
+    let client = reqwest::Client::new();
+    let result = command.run(&database, &client).await;
+    database.close().await;
+    if let Err(why) = result {
+        error!("Command failed: {why}");
+        std::process::exit(EXIT_FAILURE);
+    }
+}
+
+/// Runs a subcommand that needs the full configuration, like `check`,
+/// exits non-zero if it failed.
+async fn run_configured(command: Command) {
+    let mut config = match Config::from_env() {
+        Ok(config) => config,
+        Err(why) => {
+            println!("failed\tconfiguration: {why}");
+            std::process::exit(EXIT_CONFIG);
+        },
+    };
+    // re-mirroring re-renders the pages, mirror only just skips that.
+    if matches!(command, Command::Remirror { .. })
+        && !config.magick
+        && !config.mirror_only
+    {
+        println!("failed\timagemagick: convert not found");
+        std::process::exit(EXIT_MAGICK);
+    }
+    let database = match PgPoolOptions::new()
+        .max_connections(config.db_pool_size)
+        .connect_lazy(&config.database_url)
+    {
+        Ok(database) => database,
+        Err(why) => {
+            println!("failed\tdatabase: {why}");
+            std::process::exit(EXIT_DATABASE);
+        },
+    };
+    let clients = http_client(&config, None, None, config.s3_no_proxy)
+        .and_then(|client| {
+            let fia = http_client(
+                &config,
+                config.fia_proxy.as_ref(),
+                Some(&config.user_agent),
+                false,
+            )?;
+            Ok((client, fia))
+        });
+    let (client, fia_client) = match clients {
+        Ok(clients) => clients,
+        Err(why) => {
+            println!("failed\thttp client: {why}");
+            std::process::exit(EXIT_CONFIG);
+        },
+    };
+    let fia = FiaClient::new(fia_client, config.fia_min_interval);
+    if let Err(why) = config.resolve_season_id(&fia).await {
+        println!("failed\tseason id for {}: {why}", config.year);
+        std::process::exit(EXIT_CONFIG);
+    }
+    let passed =
+        command.run_configured(&config, &database, &client, &fia).await;
+    database.close().await;
+    if !passed {
+        std::process::exit(EXIT_FAILURE);
+    }
+}
+
+/// Builds a client with the configured timeouts, reqwest picks up
+/// `HTTP_PROXY`/`HTTPS_PROXY` on its own unless `no_proxy` is set.
+fn http_client(
+    config: &Config,
+    proxy: Option<&reqwest::Url>,
+    user_agent: Option<&str>,
+    no_proxy: bool,
+) -> Result<reqwest::Client, reqwest::Error> {
+    let mut builder = reqwest::Client::builder()
+        .timeout(config.request_timeout)
+        .connect_timeout(config.connect_timeout);
+    if let Some(user_agent) = user_agent {
+        builder = builder.user_agent(user_agent);
+    }
+    if no_proxy {
+        builder = builder.no_proxy();
+    }
+    if let Some(proxy) = proxy {
+        builder = builder.proxy(reqwest::Proxy::all(proxy.clone())?);
+    }
+    return builder.build();
 }
@@ -0,0 +1,44 @@
+use reqwest::header::CONTENT_TYPE;
+
+use crate::{
+    error::RunnerError,
+    model::{document::Document, event::Event},
+};
+
+/// Posts an embed announcing a newly mirrored document to a discord webhook.
+pub async fn notify_discord(
+    client: &reqwest::Client,
+    webhook: &str,
+    event: &Event,
+    doc: &Document,
+) -> Result<(), RunnerError> {
+    let payload = serde_json::json!({
+        "embeds": [{
+            "title": doc.title,
+            "url": doc.mirror,
+            "fields": [
+                {
+                    "name": "Series",
+                    "value": doc.series.to_string(),
+                    "inline": true,
+                },
+                {
+                    "name": "Event",
+                    "value": event.name,
+                    "inline": true,
+                },
+            ],
+            "timestamp": doc.created.to_rfc3339(),
+        }]
+    });
+
+    client
+        .post(webhook)
+        .header(CONTENT_TYPE, "application/json")
+        .body(payload.to_string())
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(RunnerError::Notify)?;
+    Ok(())
+}
@@ -0,0 +1,103 @@
+use std::time::Duration;
+
+use reqwest::{
+    header::{HeaderMap, HeaderValue, IF_RANGE, RANGE, RETRY_AFTER},
+    StatusCode,
+};
+use tokio::{sync::Mutex, time::Instant};
+use tracing::warn;
+
+const RATE_LIMIT_RETRIES: u32 = 3;
+// used when the FIA doesn't tell us how long to back off.
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(30);
+
+/// Http client for fia.com that spaces out requests so we don't get
+/// blocked, our own bucket doesn't go through this.
+pub struct FiaClient {
+    client: reqwest::Client,
+    min_interval: Duration,
+    last_request: Mutex<Option<Instant>>,
+}
+
+impl FiaClient {
+    pub fn new(
+        client: reqwest::Client,
+        min_interval: Duration,
+    ) -> Self {
+        Self {
+            client,
+            min_interval,
+            last_request: Mutex::new(None),
+        }
+    }
+
+    /// Sends a GET request once `min_interval` passed since the last one,
+    /// waiting out 429 responses as long as the FIA asks us to.
+    pub async fn get(
+        &self,
+        url: &str,
+    ) -> Result<reqwest::Response, reqwest::Error> {
+        return self.get_with(url, HeaderMap::new()).await;
+    }
+
+    /// Like `get`, but asks for the body starting at byte `offset` to resume
+    /// an interrupted download. The range only applies while the file still
+    /// matches `validator`, an etag or last modified date, otherwise the
+    /// whole file comes back with a 200.
+    pub async fn get_from(
+        &self,
+        url: &str,
+        offset: u64,
+        validator: &str,
+    ) -> Result<reqwest::Response, reqwest::Error> {
+        let mut headers = HeaderMap::new();
+        // taken from a response header, a bad one means a full download.
+        if let Ok(validator) = HeaderValue::from_str(validator) {
+            // only digits, always a valid header value.
+            headers.insert(RANGE, format!("bytes={offset}-").parse().unwrap());
+            headers.insert(IF_RANGE, validator);
+        }
+        return self.get_with(url, headers).await;
+    }
+
+    /// Like `get`, with extra request headers.
+    pub async fn get_with(
+        &self,
+        url: &str,
+        headers: HeaderMap,
+    ) -> Result<reqwest::Response, reqwest::Error> {
+        let mut attempt = 0;
+        loop {
+            self.wait_turn().await;
+            let response =
+                self.client.get(url).headers(headers.clone()).send().await?;
+            if response.status() != StatusCode::TOO_MANY_REQUESTS
+                || attempt == RATE_LIMIT_RETRIES
+            {
+                return Ok(response);
+            }
+            let retry_after = response
+                .headers()
+                .get(RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse::<u64>().ok())
+                .map_or(DEFAULT_RETRY_AFTER, Duration::from_secs);
+            warn!(
+                url,
+                retry_in = retry_after.as_secs(),
+                "Rate limited by the FIA"
+            );
+            tokio::time::sleep(retry_after).await;
+            attempt += 1;
+        }
+    }
+
+    async fn wait_turn(&self) {
+        // holding the lock while sleeping queues up concurrent runners.
+        let mut last_request = self.last_request.lock().await;
+        if let Some(last) = *last_request {
+            tokio::time::sleep_until(last + self.min_interval).await;
+        }
+        *last_request = Some(Instant::now());
+    }
+}
@@ -2,14 +2,87 @@ use std::{
     path::{Path, PathBuf},
     process::Stdio,
     str::FromStr,
+    time::Duration,
 };
 
+use tracing::{info, warn};
+
+use crate::error::RunnerError;
+
 #[cfg(target_os = "windows")]
 const CONVERT_COMMAND: &str = "magick";
 
 #[cfg(not(target_os = "windows"))]
 const CONVERT_COMMAND: &str = "convert";
 
+// mean luminance from 0 to 1 above which a page counts as blank.
+const BLANK_PAGE_LUMINANCE: f64 = 0.995;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ImageFormat {
+    #[default]
+    Jpeg,
+    Webp,
+    /// lossless, for line art that jpeg compression smears.
+    Png,
+}
+
+impl ImageFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Jpeg => "jpg",
+            Self::Webp => "webp",
+            Self::Png => "png",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Jpeg => "image/jpeg",
+            Self::Webp => "image/webp",
+            Self::Png => "image/png",
+        }
+    }
+}
+
+impl FromStr for ImageFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        return match s.to_lowercase().as_str() {
+            "jpeg" | "jpg" => Ok(Self::Jpeg),
+            "webp" => Ok(Self::Webp),
+            "png" => Ok(Self::Png),
+            _ => Err(format!("unknown image format \"{s}\"")),
+        };
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ConvertOptions {
+    /// dpi the pdf is rendered at.
+    pub density: u32,
+    /// image quality from 1 to 100.
+    pub quality: u8,
+    pub format: ImageFormat,
+    /// a malformed pdf can keep magick busy forever, it's killed after this.
+    pub timeout: Duration,
+    /// drop blank pages some pdfs render at the end.
+    pub drop_blank_pages: bool,
+}
+
+impl Default for ConvertOptions {
+    fn default() -> Self {
+        Self {
+            density: 400,
+            quality: 95,
+            format: ImageFormat::Jpeg,
+            timeout: Duration::from_secs(60),
+            drop_blank_pages: false,
+        }
+    }
+}
+
 pub fn check_magick() -> bool {
     let cmd = match std::process::Command::new("which")
         .stdout(Stdio::null())
@@ -31,53 +104,130 @@ pub fn check_magick() -> bool {
     return false;
 }
 
-pub fn run_magick(
+pub async fn run_magick(
+    tmp_dir: &Path,
     input: &str,
     output: &str,
-) -> Result<Vec<PathBuf>, String> {
-    if let Err(why) = create_doc_dir(output) {
-        return Err(format!("IO Error: {why}"));
-    }
-    let cmd = std::process::Command::new(CONVERT_COMMAND)
-        .args(["-density", "400"])
-        .arg(format!("{input}[0-100]"))
+    options: &ConvertOptions,
+) -> Result<Vec<PathBuf>, RunnerError> {
+    create_doc_dir(tmp_dir, output)?;
+    // only pdfs get split into pages, a scanned image is its single page.
+    let input = match Path::new(input).extension() {
+        Some(ext) if ext == "pdf" => format!("{input}[0-100]"),
+        _ => input.to_owned(),
+    };
+    let cmd = tokio::process::Command::new(CONVERT_COMMAND)
+        .arg("-density")
+        .arg(options.density.to_string())
+        .arg(input)
         .args(["-alpha", "remove"])
-        .args(["-quality", "95"])
-        .arg(format!("./tmp/{output}/0.jpg"))
+        .arg("-quality")
+        .arg(options.quality.to_string())
+        .arg(
+            tmp_dir
+                .join(output)
+                .join(format!("0.{}", options.format.extension())),
+        )
         .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
         .spawn();
 
     let cmd = match cmd {
         Ok(cmd) => cmd,
-        Err(why) => return Err(format!("Error running magick: {why}")),
+        Err(why) => {
+            return Err(RunnerError::Magick(format!("couldn't spawn: {why}")))
+        },
     };
 
-    if let Ok(output) = cmd.wait_with_output() {
-        if !output.status.success() {
-            let msg = String::from_utf8(output.stderr);
+    // dropping the child on timeout kills it.
+    let result =
+        match tokio::time::timeout(options.timeout, cmd.wait_with_output())
+            .await
+        {
+            Ok(result) => result,
+            Err(_) => {
+                return Err(RunnerError::Magick(format!(
+                    "timed out after {}s",
+                    options.timeout.as_secs()
+                )))
+            },
+        };
+    if let Ok(result) = result {
+        if !result.status.success() {
+            let msg = String::from_utf8(result.stderr);
             if let Ok(msg) = msg {
-                return Err(msg);
+                return Err(RunnerError::Magick(msg));
             } else {
-                return Err("Unknown error occurred running magick.".to_owned());
+                return Err(RunnerError::Magick(
+                    "Unknown error occurred running magick.".to_owned(),
+                ));
             }
         }
     }
-    return Ok(get_converted_files(output));
+    let mut files = get_converted_files(tmp_dir, output, options.format);
+    if options.drop_blank_pages {
+        let dropped = drop_blank_trailing_pages(&mut files).await;
+        if dropped > 0 {
+            info!(output, dropped, "Dropped blank trailing pages");
+        }
+    }
+    return Ok(files);
 }
 
-pub fn get_converted_files(input: &str) -> Vec<PathBuf> {
-    let mut output = vec![];
-    if let Ok(initial) = PathBuf::from_str(&format!("./tmp/{input}/0.jpg")) {
-        if initial.exists() {
-            output.push(initial);
+/// Removes blank pages from the end, always keeping the first page.
+/// Returns how many got dropped.
+async fn drop_blank_trailing_pages(files: &mut Vec<PathBuf>) -> usize {
+    let mut dropped = 0;
+    while files.len() > 1 {
+        let last = &files[files.len() - 1];
+        match mean_luminance(last).await {
+            Ok(mean) if mean >= BLANK_PAGE_LUMINANCE => {},
+            Ok(_) => break,
+            Err(why) => {
+                warn!(page = %last.display(), "Couldn't measure page: {why}");
+                break;
+            },
         }
+        files.pop();
+        dropped += 1;
+    }
+    return dropped;
+}
+
+/// Mean luminance of an image from 0 (black) to 1 (white).
+async fn mean_luminance(path: &Path) -> Result<f64, RunnerError> {
+    let output = tokio::process::Command::new(CONVERT_COMMAND)
+        .arg(path)
+        .args(["-colorspace", "Gray", "-format", "%[fx:mean]", "info:"])
+        .kill_on_drop(true)
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(RunnerError::Magick(
+            String::from_utf8_lossy(&output.stderr).into_owned(),
+        ));
+    }
+    let mean = String::from_utf8_lossy(&output.stdout);
+    return mean.trim().parse::<f64>().map_err(|_| {
+        RunnerError::Magick(format!("unexpected mean \"{}\"", mean.trim()))
+    });
+}
+
+pub fn get_converted_files(
+    tmp_dir: &Path,
+    input: &str,
+    format: ImageFormat,
+) -> Vec<PathBuf> {
+    let ext = format.extension();
+    let dir = tmp_dir.join(input);
+    let mut output = vec![];
+    let initial = dir.join(format!("0.{ext}"));
+    if initial.exists() {
+        output.push(initial);
     }
     for i in 0..=100 {
-        let path = match PathBuf::from_str(&format!("./tmp/{input}/0-{i}.jpg"))
-        {
-            Err(_) => continue,
-            Ok(path) => path,
-        };
+        let path = dir.join(format!("0-{i}.{ext}"));
         // if our file doesn't exist there won't be others anyways.
         if !path.exists() {
             break;
@@ -87,25 +237,46 @@ pub fn get_converted_files(input: &str) -> Vec<PathBuf> {
     return output;
 }
 
-pub fn create_tmp_dir() -> Result<(), std::io::Error> {
-    let path = Path::new("./tmp");
-    if !path.exists() {
-        std::fs::create_dir("./tmp")?;
+pub fn create_tmp_dir(tmp_dir: &Path) -> Result<(), std::io::Error> {
+    if !tmp_dir.exists() {
+        std::fs::create_dir_all(tmp_dir)?;
     };
     return Ok(());
 }
 
-pub fn create_doc_dir(filename: &str) -> Result<(), std::io::Error> {
-    let pathname = format!("./tmp/{filename}/");
-    let path = Path::new(&pathname);
+pub fn create_doc_dir(
+    tmp_dir: &Path,
+    filename: &str,
+) -> Result<(), std::io::Error> {
+    let path = tmp_dir.join(filename);
     if !path.exists() {
-        std::fs::create_dir(pathname)?;
+        std::fs::create_dir(path)?;
     }
     return Ok(());
 }
 
-pub fn clear_tmp_dir() -> Result<(), std::io::Error> {
-    std::fs::remove_dir_all("./tmp/")?;
-    create_tmp_dir()?;
+pub fn clear_tmp_dir(tmp_dir: &Path) -> Result<(), std::io::Error> {
+    std::fs::remove_dir_all(tmp_dir)?;
+    create_tmp_dir(tmp_dir)?;
+    return Ok(());
+}
+
+/// Removes the temp files and page dirs whose name starts with `prefix`,
+/// leaving files of other runners alone.
+pub fn clear_tmp_files(
+    tmp_dir: &Path,
+    prefix: &str,
+) -> Result<(), std::io::Error> {
+    for entry in std::fs::read_dir(tmp_dir)? {
+        let entry = entry?;
+        if !entry.file_name().to_string_lossy().starts_with(prefix) {
+            continue;
+        }
+        if entry.file_type()?.is_dir() {
+            std::fs::remove_dir_all(entry.path())?;
+        } else {
+            std::fs::remove_file(entry.path())?;
+        }
+    }
     return Ok(());
 }
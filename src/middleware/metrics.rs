@@ -0,0 +1,134 @@
+use std::{
+    fmt::Write,
+    sync::{
+        atomic::{AtomicI64, AtomicU64, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use axum::{http::StatusCode, routing::get, Router};
+use chrono::Utc;
+
+use crate::model::series::Series;
+
+pub static METRICS: Metrics = Metrics::new();
+
+/// Counters of the runner, exposed in prometheus' text format.
+pub struct Metrics {
+    pub documents_discovered: AtomicU64,
+    pub documents_mirrored: AtomicU64,
+    pub upload_failures: AtomicU64,
+    pub parse_failures: AtomicU64,
+    cycle_duration_ms: AtomicU64,
+    /// unix timestamp of the last cycle that finished without hard errors.
+    last_success: AtomicI64,
+    /// per series, when its last scan finished and how many documents it saw.
+    series_scans: Mutex<Vec<(Series, i64, u64)>>,
+}
+
+impl Metrics {
+    const fn new() -> Self {
+        Self {
+            documents_discovered: AtomicU64::new(0),
+            documents_mirrored: AtomicU64::new(0),
+            upload_failures: AtomicU64::new(0),
+            parse_failures: AtomicU64::new(0),
+            cycle_duration_ms: AtomicU64::new(0),
+            last_success: AtomicI64::new(0),
+            series_scans: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn inc(counter: &AtomicU64) {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn finish_cycle(
+        &self,
+        duration: Duration,
+        completed: bool,
+    ) {
+        self.cycle_duration_ms
+            .store(duration.as_millis() as u64, Ordering::Relaxed);
+        if completed {
+            self.last_success.store(Utc::now().timestamp(), Ordering::Relaxed);
+        }
+    }
+
+    pub fn record_scan(
+        &self,
+        series: Series,
+        document_count: u64,
+    ) {
+        let now = Utc::now().timestamp();
+        let mut scans = match self.series_scans.lock() {
+            Ok(scans) => scans,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        match scans.iter_mut().find(|(s, ..)| *s == series) {
+            Some(scan) => *scan = (series, now, document_count),
+            None => scans.push((series, now, document_count)),
+        }
+    }
+
+    fn render(&self) -> String {
+        let mut out = String::new();
+        let counters = [
+            ("fia_documents_discovered_total", &self.documents_discovered),
+            ("fia_documents_mirrored_total", &self.documents_mirrored),
+            ("fia_upload_failures_total", &self.upload_failures),
+            ("fia_parse_failures_total", &self.parse_failures),
+        ];
+        for (name, counter) in counters {
+            let _ = writeln!(out, "# TYPE {name} counter");
+            let _ = writeln!(out, "{name} {}", counter.load(Ordering::Relaxed));
+        }
+        let cycle = self.cycle_duration_ms.load(Ordering::Relaxed) as f64;
+        let _ = writeln!(out, "# TYPE fia_cycle_duration_seconds gauge");
+        let _ = writeln!(out, "fia_cycle_duration_seconds {}", cycle / 1000.0);
+        let scans = match self.series_scans.lock() {
+            Ok(scans) => scans.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        };
+        let _ = writeln!(out, "# TYPE fia_series_last_scanned_seconds gauge");
+        for (series, last_scanned, _) in &scans {
+            let _ = writeln!(
+                out,
+                "fia_series_last_scanned_seconds{{series=\"{series}\"}} {last_scanned}"
+            );
+        }
+        let _ = writeln!(out, "# TYPE fia_series_documents gauge");
+        for (series, _, documents) in &scans {
+            let _ = writeln!(
+                out,
+                "fia_series_documents{{series=\"{series}\"}} {documents}"
+            );
+        }
+        return out;
+    }
+}
+
+/// Serves `/healthz` and `/metrics`, healthy means a cycle completed
+/// within `max_age`.
+pub async fn serve_metrics(
+    port: u16,
+    max_age: Duration,
+) -> Result<(), std::io::Error> {
+    let app = Router::new()
+        .route(
+            "/healthz",
+            get(move || async move {
+                let last = METRICS.last_success.load(Ordering::Relaxed);
+                let age = Utc::now().timestamp() - last;
+                if age <= max_age.as_secs() as i64 {
+                    return (StatusCode::OK, "ok");
+                }
+                return (StatusCode::SERVICE_UNAVAILABLE, "stale");
+            }),
+        )
+        .route("/metrics", get(|| async { METRICS.render() }));
+    let listener = tokio::net::TcpListener::bind(("0.0.0.0", port)).await?;
+    axum::serve(listener, app).await?;
+    return Ok(());
+}
@@ -1,4 +1,12 @@
 mod cache;
+pub mod discord;
+pub mod fia;
 pub mod magick;
+pub mod metrics;
 pub mod parser;
+#[cfg(feature = "phash")]
+pub mod phash;
 pub mod runner;
+pub mod s3;
+pub mod sportity;
+pub mod storage;
@@ -1,11 +1,48 @@
 use std::num::NonZeroI16;
 
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Europe::Paris;
 use html5ever::{
-    tokenizer::{Tag, TagKind::StartTag, Token, TokenSink, TokenSinkResult},
+    tendril::{ByteTendril, ReadExt},
+    tokenizer::{
+        BufferQueue, Tag,
+        TagKind::{EndTag, StartTag},
+        Token, TokenSink, TokenSinkResult, Tokenizer, TokenizerOpts,
+    },
     Attribute,
 };
 
-const BASE_URL: &str = "https://www.fia.com";
+use reqwest::{
+    header::{
+        HeaderMap, HeaderName, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH,
+        LAST_MODIFIED,
+    },
+    StatusCode,
+};
+use tracing::warn;
+
+use super::fia::FiaClient;
+use crate::{error::RunnerError, model::series::FIA_URL};
+
+// its season picker links every season the FIA has documents for.
+const SEASONS_PATH: &str =
+    "/documents/championships/fia-formula-one-world-championship-14";
+/// Stored with every document, bump it when a fix changes titles or
+/// classifications so `REFRESH_METADATA` updates older documents.
+pub const PARSER_VERSION: i32 = 3;
+// formats the FIA used over the seasons, tried in order.
+// two digit years first, `%Y` also takes "24" and reads it as the year 24.
+const DATE_TIME_FORMATS: [&str; 5] = [
+    "%d.%m.%y %H:%M",
+    "%d.%m.%Y %H:%M",
+    "%d/%m/%y %H:%M",
+    "%d/%m/%Y %H:%M",
+    "%Y-%m-%dT%H:%M:%S",
+];
+const DATE_FORMATS: [&str; 4] =
+    ["%d.%m.%y", "%d.%m.%Y", "%d/%m/%y", "%d/%m/%Y"];
+// a season has a few dozen events at most, more pages means a pager loop.
+const MAX_PAGES: usize = 20;
 
 enum ParserState {
     None,
@@ -17,17 +54,45 @@ enum ParserState {
     Next,
 }
 
+/// A document with everything the runner needs, incomplete ones never make
+/// it out of the parser.
 #[derive(Clone, Debug)]
 pub struct ParserDocument {
-    pub title: Option<String>,
-    pub url: Option<String>,
-    pub date: Option<String>,
+    pub title: String,
+    pub url: String,
+    pub date: String,
+}
+
+/// A document as its fields trickle in from the tokenizer.
+#[derive(Default)]
+struct DocumentBuilder {
+    title: Option<String>,
+    url: Option<String>,
+    date: Option<String>,
+}
+
+impl DocumentBuilder {
+    fn build(self) -> Option<ParserDocument> {
+        return match (self.title, self.url, self.date) {
+            (Some(title), Some(url), Some(date)) => Some(ParserDocument {
+                title,
+                url,
+                date,
+            }),
+            (title, url, date) => {
+                warn!(?title, ?url, ?date, "Skipping incomplete document");
+                None
+            },
+        };
+    }
 }
 
 #[derive(Debug)]
 pub struct Season {
     pub year: NonZeroI16,
     pub events: Vec<ParserEvent>,
+    /// href of the pager's next page, relative to the page it's on.
+    pub next_page: Option<String>,
 }
 
 #[derive(Debug)]
@@ -35,26 +100,98 @@ pub struct ParserEvent {
     pub title: Option<String>,
     pub season: Option<NonZeroI16>,
     pub documents: Vec<ParserDocument>,
+    /// documents skipped for a missing title, url or date.
+    pub incomplete: usize,
 }
 
 pub struct HTMLParser<'a> {
     state: ParserState,
     pub season: &'a mut Season,
+    /// scheme and host the page's relative links point to.
+    base: &'a str,
     event: Option<ParserEvent>,
-    document: Option<ParserDocument>,
+    /// the title of the next event, until its div closes.
+    event_title: String,
+    document: Option<DocumentBuilder>,
 }
 
 impl<'a> HTMLParser<'a> {
-    pub fn new(season: &'a mut Season) -> Self {
+    pub fn new(
+        season: &'a mut Season,
+        base: &'a str,
+    ) -> Self {
         Self {
             state: ParserState::None,
             season,
+            base,
             event: None,
+            event_title: String::new(),
             document: None,
         }
     }
 }
 
+/// Parses a document's "Published on" date. The FIA doesn't include an offset
+/// and publishes in CET/CEST regardless of where the event takes place, dates
+/// without a time are taken as midnight.
+pub fn parse_date(date: &str) -> Option<DateTime<Utc>> {
+    let date = date.trim();
+    if let Ok(date) = DateTime::parse_from_rfc3339(date) {
+        return Some(date.with_timezone(&Utc));
+    }
+    let naive = DATE_TIME_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(date, format).ok())
+        .or_else(|| {
+            DATE_FORMATS.iter().find_map(|format| {
+                NaiveDate::parse_from_str(date, format)
+                    .ok()
+                    .and_then(|date| date.and_hms_opt(0, 0, 0))
+            })
+        })?;
+    let local = Paris.from_local_datetime(&naive).earliest()?;
+    return Some(local.with_timezone(&Utc));
+}
+
+/// Finds the FIA's id for `year` in a documents page, which links every
+/// season as `season-{year}-{id}`.
+pub fn find_season_id(
+    html: &str,
+    year: i32,
+) -> Option<u32> {
+    let marker = format!("season-{year}-");
+    return html.match_indices(&marker).find_map(|(start, _)| {
+        let id: String = html[start + marker.len()..]
+            .chars()
+            .take_while(|c| c.is_ascii_digit())
+            .collect();
+        id.parse().ok()
+    });
+}
+
+/// Looks up the id of a season on the F1 documents page of fia.com, reached
+/// at `base`.
+pub async fn fetch_season_id(
+    fia: &FiaClient,
+    base: &str,
+    year: i32,
+) -> Result<u32, RunnerError> {
+    let url = format!("{base}{SEASONS_PATH}");
+    let response = fia.get(&url).await.map_err(RunnerError::Download)?;
+    if !response.status().is_success() {
+        return Err(RunnerError::status(&response));
+    }
+    let html = response.text().await.map_err(RunnerError::Download)?;
+    return find_season_id(&html, year).ok_or_else(|| {
+        RunnerError::Parse(format!("no season {year} on {url}"))
+    });
+}
+
+/// Collapses runs of whitespace and newlines into single spaces.
+pub(crate) fn normalize_whitespace(text: &str) -> String {
+    return text.split_whitespace().collect::<Vec<_>>().join(" ");
+}
+
 fn get_attr<'a>(
     tag: &Tag,
     name: &str,
@@ -64,6 +201,16 @@ fn get_attr<'a>(
     return attr;
 }
 
+/// Drupal pagers mark the next page either with `rel="next"` or, on older
+/// themes, with a "Go to next page" title.
+fn is_next_page_link(tag: &Tag) -> bool {
+    if get_attr(tag, "rel").is_some_and(|rel| rel.value.as_ref() == "next") {
+        return true;
+    }
+    return get_attr(tag, "title")
+        .is_some_and(|title| title.value.as_ref() == "Go to next page");
+}
+
 impl<'a> TokenSink for HTMLParser<'a> {
     type Handle = ();
 
@@ -78,32 +225,39 @@ impl<'a> TokenSink for HTMLParser<'a> {
                 let class = get_attr(&tag_token, "class");
                 match (tag_token.kind, name) {
                     (StartTag, "ul") => {
-                        if class.unwrap().value.as_ref() == "event-wrapper" {
+                        if class.is_some_and(|class| {
+                            class.value.as_ref() == "event-wrapper"
+                        }) {
                             self.state = ParserState::BeginEvent;
                         }
                     },
                     (StartTag, "a") => {
+                        if is_next_page_link(&tag_token) {
+                            self.season.next_page =
+                                get_attr(&tag_token, "href")
+                                    .map(|href| href.value.trim().to_owned());
+                            return TokenSinkResult::Continue;
+                        }
                         match self.state {
                             ParserState::Next => {},
                             _ => {
                                 return TokenSinkResult::Continue;
                             },
                         }
-                        if let Some(href) =
-                            get_attr(&tag_token, "href").as_ref()
-                        {
-                            let href = href.value.as_ref();
-                            self.document = Some(ParserDocument {
-                                url: Some(format!(
-                                    "{}{}",
-                                    BASE_URL,
-                                    href.trim().replace(' ', "%20")
-                                )),
-                                title: None,
-                                date: None,
-                            });
-                            self.state = ParserState::Document;
-                        }
+                        // kept without a url so the document counts as
+                        // incomplete instead of vanishing.
+                        let url = get_attr(&tag_token, "href").map(|href| {
+                            format!(
+                                "{}{}",
+                                self.base,
+                                href.value.trim().replace(' ', "%20")
+                            )
+                        });
+                        self.document = Some(DocumentBuilder {
+                            url,
+                            ..Default::default()
+                        });
+                        self.state = ParserState::Document;
                     },
                     (StartTag, "div") => {
                         if class.is_none() {
@@ -126,41 +280,63 @@ impl<'a> TokenSink for HTMLParser<'a> {
                     },
                     (StartTag, "span") => match self.state {
                         ParserState::Document => {
-                            if class.as_ref().unwrap().value.as_ref()
-                                == "date-display-single"
-                            {
+                            if class.is_some_and(|class| {
+                                class.value.as_ref() == "date-display-single"
+                            }) {
                                 self.state = ParserState::DocumentDate;
                             }
                         },
                         _ => {},
                     },
+                    (EndTag, "div") => {
+                        // titles arrive in pieces, split at newlines,
+                        // entities and nested tags, only the closing div
+                        // ends them.
+                        if let ParserState::EventTitle = self.state {
+                            let title = normalize_whitespace(&self.event_title);
+                            self.event_title.clear();
+                            // an empty title div, wait for the real one.
+                            if title.is_empty() {
+                                self.state = ParserState::BeginEvent;
+                                return TokenSinkResult::Continue;
+                            }
+                            if let Some(event) = self.event.take() {
+                                self.season.events.push(event);
+                            }
+                            self.event = Some(ParserEvent {
+                                season: Some(self.season.year),
+                                title: Some(title),
+                                documents: Vec::with_capacity(60),
+                                incomplete: 0,
+                            });
+                            self.state = ParserState::Next;
+                        }
+                        if let ParserState::DocumentTitle = self.state {
+                            if let Some(doc) = self.document.as_mut() {
+                                doc.title = doc
+                                    .title
+                                    .as_deref()
+                                    .map(normalize_whitespace)
+                                    .filter(|title| !title.is_empty());
+                            }
+                            self.state = ParserState::Document;
+                        }
+                    },
 
                     _ => {},
                 }
             },
             Token::CharacterTokens(chars) => match self.state {
                 ParserState::EventTitle => {
-                    if chars.trim().len() == 0 {
-                        return TokenSinkResult::Continue;
-                    }
-                    if let Some(event) = self.event.take() {
-                        self.season.events.push(event);
-                    }
-                    let event = ParserEvent {
-                        season: Some(self.season.year),
-                        title: Some(chars.trim().to_owned()),
-                        documents: Vec::with_capacity(60),
-                    };
-                    self.state = ParserState::Next;
-                    self.event = Some(event);
+                    self.event_title.push_str(&chars);
                 },
                 ParserState::DocumentTitle => {
-                    if chars.trim().len() == 0 {
-                        return TokenSinkResult::Continue;
-                    }
-                    self.document.as_mut().unwrap().title =
-                        Some(chars.trim().to_owned());
-                    self.state = ParserState::Document;
+                    self.document
+                        .as_mut()
+                        .unwrap()
+                        .title
+                        .get_or_insert_with(String::new)
+                        .push_str(&chars);
                 },
                 ParserState::DocumentDate => {
                     if chars.trim().len() == 0 {
@@ -169,8 +345,11 @@ impl<'a> TokenSink for HTMLParser<'a> {
                     self.document.as_mut().unwrap().date =
                         Some(chars.trim().to_owned());
                     self.state = ParserState::Next;
-                    if let Some(doc) = self.document.take() {
-                        self.event.as_mut().unwrap().documents.push(doc);
+                    let event = self.event.as_mut().unwrap();
+                    match self.document.take().and_then(DocumentBuilder::build)
+                    {
+                        Some(doc) => event.documents.push(doc),
+                        None => event.incomplete += 1,
                     }
                 },
                 ParserState::Document => {},
@@ -186,3 +365,315 @@ impl<'a> TokenSink for HTMLParser<'a> {
         return TokenSinkResult::Continue;
     }
 }
+
+/// Cache validators of a season's first page, from its last fetch.
+#[derive(Clone, Debug, Default)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+/// Fetches and parses a season's documents page, following the pager so
+/// events on later pages aren't missed.
+pub async fn get_season(
+    fia: &FiaClient,
+    url: &str,
+    year: NonZeroI16,
+) -> Result<Season, RunnerError> {
+    let mut validators = Validators::default();
+    return get_season_if_modified(fia, url, year, &mut validators)
+        .await?
+        .ok_or_else(|| {
+            RunnerError::Parse(format!("{url} unexpectedly wasn't modified"))
+        });
+}
+
+/// Like `get_season`, but returns none if the first page didn't change since
+/// `validators` were taken. They're updated from the response.
+pub async fn get_season_if_modified(
+    fia: &FiaClient,
+    url: &str,
+    year: NonZeroI16,
+    validators: &mut Validators,
+) -> Result<Option<Season>, RunnerError> {
+    let mut page_url = reqwest::Url::parse(url)
+        .map_err(|why| RunnerError::Parse(format!("bad url {url}: {why}")))?;
+    let mut season = Season {
+        year,
+        events: vec![],
+        next_page: None,
+    };
+    let mut visited: Vec<reqwest::Url> = vec![];
+    loop {
+        // later pages move whenever the first one does.
+        let mut headers = HeaderMap::new();
+        if visited.is_empty() {
+            if let Some(etag) = &validators.etag {
+                headers.insert(IF_NONE_MATCH, etag.parse()?);
+            }
+            if let Some(last_modified) = &validators.last_modified {
+                headers.insert(IF_MODIFIED_SINCE, last_modified.parse()?);
+            }
+        }
+        let response = fia
+            .get_with(page_url.as_str(), headers)
+            .await
+            .map_err(RunnerError::Download)?;
+        if visited.is_empty() {
+            if response.status() == StatusCode::NOT_MODIFIED {
+                return Ok(None);
+            }
+            let header = |name: HeaderName| {
+                response
+                    .headers()
+                    .get(name)
+                    .and_then(|value| value.to_str().ok())
+                    .map(|value| value.to_owned())
+            };
+            *validators = Validators {
+                etag: header(ETAG),
+                last_modified: header(LAST_MODIFIED),
+            };
+        }
+        if !response.status().is_success() {
+            return Err(RunnerError::status(&response));
+        }
+        let bytes = response.bytes().await.map_err(RunnerError::Download)?;
+        let origin = page_url.origin().ascii_serialization();
+        let page = parse_page(&bytes, year, &origin)?;
+        visited.push(page_url.clone());
+        merge_events(&mut season, page.events);
+
+        let Some(next) = page.next_page else {
+            break;
+        };
+        page_url = page_url.join(&next).map_err(|why| {
+            RunnerError::Parse(format!("bad next page {next}: {why}"))
+        })?;
+        if visited.contains(&page_url) || visited.len() == MAX_PAGES {
+            warn!(url, pages = visited.len(), "Stopped following the pager");
+            break;
+        }
+    }
+    return Ok(Some(season));
+}
+
+/// Adds a page's events to the season, an event split across two pages
+/// gets its documents joined.
+fn merge_events(
+    season: &mut Season,
+    events: Vec<ParserEvent>,
+) {
+    for event in events {
+        match season.events.iter_mut().find(|e| e.title == event.title) {
+            Some(existing) => {
+                existing.documents.extend(event.documents);
+                existing.incomplete += event.incomplete;
+            },
+            None => season.events.push(event),
+        }
+    }
+}
+
+/// Parses the html of a season's documents page, without any io.
+pub fn parse_season(
+    html: &[u8],
+    year: NonZeroI16,
+) -> Result<Season, RunnerError> {
+    return parse_page(html, year, FIA_URL);
+}
+
+/// Like `parse_season`, with relative links resolved against `base`.
+fn parse_page(
+    html: &[u8],
+    year: NonZeroI16,
+    base: &str,
+) -> Result<Season, RunnerError> {
+    let mut tendril = ByteTendril::new();
+    let mut html = html;
+    html.read_to_tendril(&mut tendril)?;
+    let tendril = tendril.try_reinterpret().map_err(|_| {
+        RunnerError::Parse("season page isn't valid utf-8".to_owned())
+    })?;
+    let mut input = BufferQueue::new();
+    input.push_back(tendril);
+    let mut season = Season {
+        year,
+        events: vec![],
+        next_page: None,
+    };
+    let sink = HTMLParser::new(&mut season, base);
+    let mut tok = Tokenizer::new(sink, TokenizerOpts::default());
+    let _ = tok.feed(&mut input);
+    tok.end();
+    return Ok(season);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use wiremock::{matchers::path, Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+
+    fn year() -> NonZeroI16 {
+        return NonZeroI16::new(2024).unwrap();
+    }
+
+    #[test]
+    fn follows_and_merges_a_multi_page_season() {
+        let first = parse_season(
+            include_bytes!("../../tests/fixtures/pager_page1.html"),
+            year(),
+        )
+        .unwrap();
+        assert_eq!(first.next_page.as_deref(), Some("?page=1"));
+        let second = parse_season(
+            include_bytes!("../../tests/fixtures/pager_page2.html"),
+            year(),
+        )
+        .unwrap();
+        // the last page only links back.
+        assert_eq!(second.next_page, None);
+
+        let mut season = Season {
+            year: year(),
+            events: vec![],
+            next_page: None,
+        };
+        merge_events(&mut season, first.events);
+        merge_events(&mut season, second.events);
+        let titles: Vec<&str> = season
+            .events
+            .iter()
+            .map(|event| event.title.as_deref().unwrap())
+            .collect();
+        assert_eq!(
+            titles,
+            [
+                "Monaco Grand Prix",
+                "Emilia Romagna Grand Prix",
+                "Miami Grand Prix"
+            ]
+        );
+        // split across both pages, joined into one event.
+        let imola: Vec<&str> = season.events[1]
+            .documents
+            .iter()
+            .map(|doc| doc.title.as_str())
+            .collect();
+        assert_eq!(
+            imola,
+            ["Doc 49 - Final Race Classification", "Doc 1 - Event Notes"]
+        );
+    }
+
+    #[test]
+    fn detects_older_pager_themes() {
+        let html = br#"<ul class="pager"><li class="pager-next"><a title="Go to next page" href="?page=2">next</a></li></ul>"#;
+        let season = parse_season(html, year()).unwrap();
+        assert_eq!(season.next_page.as_deref(), Some("?page=2"));
+    }
+
+    #[test]
+    fn ignores_tags_without_a_class() {
+        let html = br#"<ul><li>menu</li></ul>
+            <ul class="event-wrapper"><li>
+            <div class="event-title active">Monaco Grand Prix</div>
+            <ul class="document-row-wrapper"><li class="document-row key-1">
+            <a href="/doc1.pdf"><div class="title">Doc 1 - Event Notes</div>
+            <div class="published">Published on <span>new</span>
+            <span class="date-display-single">24.05.24 10:00</span></div>
+            </a></li></ul></li></ul>"#;
+        let season = parse_season(html, year()).unwrap();
+        assert_eq!(season.events.len(), 1);
+        let docs = &season.events[0].documents;
+        assert_eq!(docs.len(), 1);
+        assert_eq!(docs[0].date, "24.05.24 10:00");
+    }
+
+    fn utc(date: &str) -> Option<String> {
+        return parse_date(date).map(|date| date.to_rfc3339());
+    }
+
+    #[test]
+    fn parses_every_date_format() {
+        let expected = Some("2024-03-02T18:27:00+00:00".to_owned());
+        assert_eq!(utc("02.03.24 19:27"), expected);
+        assert_eq!(utc("02.03.2024 19:27"), expected);
+        assert_eq!(utc("02/03/24 19:27"), expected);
+        assert_eq!(utc("02/03/2024 19:27"), expected);
+        assert_eq!(utc("2024-03-02T19:27:00"), expected);
+        assert_eq!(utc(" 2024-03-02T19:27:00+01:00 "), expected);
+
+        let midnight = Some("2024-03-01T23:00:00+00:00".to_owned());
+        assert_eq!(utc("02.03.24"), midnight);
+        assert_eq!(utc("02.03.2024"), midnight);
+        assert_eq!(utc("02/03/24"), midnight);
+        assert_eq!(utc("02/03/2024"), midnight);
+        // %Y takes "24" as well, it must not become the year 24.
+        let year = utc("02/03/24").unwrap();
+        assert!(year.starts_with("2024-"), "{year}");
+
+        assert_eq!(utc("Published on"), None);
+        assert_eq!(utc(""), None);
+    }
+
+    #[test]
+    fn converts_paris_time_across_dst() {
+        assert_eq!(
+            utc("01.01.24 12:00").as_deref(),
+            Some("2024-01-01T11:00:00+00:00")
+        );
+        assert_eq!(
+            utc("01.07.24 12:00").as_deref(),
+            Some("2024-07-01T10:00:00+00:00")
+        );
+        // clocks go forward at 02:00 on the last sunday of march.
+        assert_eq!(
+            utc("31.03.24 01:30").as_deref(),
+            Some("2024-03-31T00:30:00+00:00")
+        );
+        assert_eq!(utc("31.03.24 02:30"), None);
+        assert_eq!(
+            utc("31.03.24 03:30").as_deref(),
+            Some("2024-03-31T01:30:00+00:00")
+        );
+        // and back at 03:00 in october, the repeated hour is taken as CEST.
+        assert_eq!(
+            utc("27.10.24 02:30").as_deref(),
+            Some("2024-10-27T00:30:00+00:00")
+        );
+        assert_eq!(
+            utc("27.10.24 03:30").as_deref(),
+            Some("2024-10-27T02:30:00+00:00")
+        );
+    }
+
+    #[test]
+    fn finds_the_season_id_in_the_season_picker() {
+        let html = r#"<select>
+            <option value="/documents/championships/fia-formula-one-world-championship-14/season/season-2026-2072">2026</option>
+            <option value="/documents/championships/fia-formula-one-world-championship-14/season/season-2025-2071">2025</option>
+        </select>"#;
+        assert_eq!(find_season_id(html, 2025), Some(2071));
+        assert_eq!(find_season_id(html, 2026), Some(2072));
+        assert_eq!(find_season_id(html, 2027), None);
+    }
+
+    #[tokio::test]
+    async fn fetches_the_season_id_from_the_configured_base() {
+        let fia_mock = MockServer::start().await;
+        Mock::given(path(SEASONS_PATH))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"<a href="/documents/championships/fia-formula-one-world-championship-14/season/season-2026-2072">2026</a>"#,
+            ))
+            .mount(&fia_mock)
+            .await;
+        let fia = FiaClient::new(reqwest::Client::new(), Duration::ZERO);
+        let season_id = fetch_season_id(&fia, &fia_mock.uri(), 2026).await;
+        assert_eq!(season_id.unwrap(), 2072);
+        assert!(fetch_season_id(&fia, &fia_mock.uri(), 2027).await.is_err());
+    }
+}
@@ -0,0 +1,27 @@
+use image_hasher::{HashAlg, HasherConfig};
+use tracing::warn;
+
+/// Perceptual hash of a page image, close hashes mean visually similar
+/// pages even if their bytes differ.
+pub async fn page_hash(buf: &[u8]) -> Option<String> {
+    let buf = buf.to_owned();
+    // decoding a 400dpi page is too slow for the async runtime.
+    let hash = tokio::task::spawn_blocking(move || {
+        let image = image::load_from_memory(&buf)?;
+        let hasher =
+            HasherConfig::new().hash_alg(HashAlg::Gradient).to_hasher();
+        Ok::<_, image::ImageError>(hasher.hash_image(&image).to_base64())
+    })
+    .await;
+    return match hash {
+        Ok(Ok(hash)) => Some(hash),
+        Ok(Err(why)) => {
+            warn!("Couldn't decode page for hashing: {why}");
+            None
+        },
+        Err(why) => {
+            warn!("Page hashing panicked: {why}");
+            None
+        },
+    };
+}
@@ -1,45 +1,98 @@
 use super::{
-    magick::{clear_tmp_dir, run_magick},
-    parser::{HTMLParser, ParserEvent},
+    discord::notify_discord,
+    fia::FiaClient,
+    magick::{
+        clear_tmp_files, create_tmp_dir, run_magick, ConvertOptions,
+        ImageFormat,
+    },
+    metrics::{Metrics, METRICS},
+    parser::{
+        get_season, get_season_if_modified, parse_date, ParserDocument,
+        ParserEvent, Validators, PARSER_VERSION,
+    },
+    s3::key_segment,
+    sportity, storage,
+};
+use crate::{
+    config::{Config, StorageConfig},
+    error::RunnerError,
+    model::{
+        document::{
+            doc_number, is_revision, revises, Document, DocumentType, MediaType,
+        },
+        event::Event,
+        series::Series,
+        source::Source,
+    },
 };
-use crate::model::{event::Event, series::Series};
-use aws_sign_v4::AwsSign;
 use chrono::DateTime;
-use html5ever::{
-    tendril::{ByteTendril, ReadExt},
-    tokenizer::{BufferQueue, Tokenizer, TokenizerOpts},
+use futures::StreamExt;
+use rand::Rng;
+use reqwest::{
+    header::{
+        HeaderName, ACCEPT_RANGES, CONTENT_DISPOSITION, CONTENT_TYPE, ETAG,
+        LAST_MODIFIED,
+    },
+    StatusCode,
 };
-use reqwest::header::{AUTHORIZATION, CONTENT_TYPE};
-use sqlx::{types::chrono::Utc, Pool, Postgres};
+use sqlx::{types::chrono::Utc, PgExecutor, Pool, Postgres};
+use tracing::{debug, error, info, warn};
+
+use std::time::UNIX_EPOCH;
 use std::{
-    error::Error, fs::File, num::NonZeroI16, path::PathBuf, str::FromStr,
+    collections::HashSet,
+    num::NonZeroI16,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
     time::Duration,
 };
-use std::{
-    io::{Read, Write},
-    time::UNIX_EPOCH,
-};
 
-const F1_DOCS_URL:&str = "https://www.fia.com/documents/championships/fia-formula-one-world-championship-14/season/season-2024-2043";
-const F2_DOCS_URL:&str = "https://www.fia.com/documents/season/season-2024-2043/championships/formula-2-championship-44";
-const F3_DOCS_URL:&str = "https://www.fia.com/documents/season/season-2024-2043/championships/fia-formula-3-championship-1012";
-const YEAR: f64 = 2024.0;
+const PAGE_UPLOAD_CONCURRENCY: usize = 4;
+const DOWNLOAD_RETRIES: u32 = 3;
 
 struct MinDoc {
     pub url: String,
 }
 
 struct LocalCache {
-    pub documents: Vec<MinDoc>,
+    /// urls of documents already in the database, the database stays the
+    /// source of truth, this only saves the lookup for known documents.
+    pub documents: HashSet<String>,
     pub events: Vec<Event>,
     pub last_populated: DateTime<Utc>,
 }
 
+/// What a single scan of a series did, logged once it finishes.
+#[derive(Default)]
+struct ScanStats {
+    pub events: usize,
+    pub documents_found: usize,
+    pub documents_mirrored: usize,
+    pub pages_uploaded: usize,
+    pub errors: usize,
+}
+
+impl ScanStats {
+    fn add(
+        &mut self,
+        other: &Self,
+    ) {
+        self.events += other.events;
+        self.documents_found += other.documents_found;
+        self.documents_mirrored += other.documents_mirrored;
+        self.pages_uploaded += other.pages_uploaded;
+        self.errors += other.errors;
+    }
+}
+
 impl Default for LocalCache {
     fn default() -> Self {
         Self {
             events: vec![],
-            documents: vec![],
+            documents: HashSet::new(),
             last_populated: DateTime::from(UNIX_EPOCH),
         }
     }
@@ -49,6 +102,7 @@ async fn populate_cache(
     pool: &Pool<Postgres>,
     cache: &mut LocalCache,
     series: Series,
+    year: i32,
 ) {
     let delta = Utc::now() - cache.last_populated;
     // lets revalidate the cache once a day.
@@ -56,37 +110,42 @@ async fn populate_cache(
         return;
     }
     let series_str: String = series.into();
+    // select by the season of the event, not the year a document was
+    // created in. documents published after midnight on new year's eve
+    // would otherwise fall out of their season's cache.
     let docs: Vec<MinDoc> = match sqlx::query_as!(
         MinDoc,
         r#"
-    SELECT url
-    FROM documents
-    WHERE series = $1 AND EXTRACT('Year' from created) = $2"#,
+    SELECT d.url
+    FROM documents d
+    JOIN events e ON e.id = d.event
+    WHERE d.series = $1 AND e.year = $2"#,
         series_str,
-        YEAR
+        year
     )
     .fetch_all(pool)
     .await
     {
         Ok(data) => data,
         Err(why) => {
-            eprintln!("Error populating cache: {why}");
+            error!(%series, "Error populating cache: {why}");
             return;
         },
     };
 
+    // series decode through sqlx::Type, an unknown series is an error.
     let events: Vec<Event> = match sqlx::query_as_unchecked!(
         Event,
         r#"SELECT 
         id as "id?", 
         year, 
-        series, 
+        series as "series: Series", 
         name, 
         created 
         FROM
         events where year = $1 AND 
         series = $2"#,
-        YEAR,
+        year,
         series_str
     )
     .fetch_all(pool)
@@ -94,89 +153,231 @@ async fn populate_cache(
     {
         Ok(data) => data,
         Err(why) => {
-            eprintln!("Error populating events: {why}");
+            error!(%series, "Error populating events: {why}");
             return;
         },
     };
     cache.events = events;
-    cache.documents = docs;
+    cache.documents = docs.into_iter().map(|doc| doc.url).collect();
     cache.last_populated = Utc::now();
-    println!("Repopulated cache!");
-    println!(
-        "{series} events: {}, docs: {}",
-        cache.events.len(),
-        cache.documents.len()
+    info!(
+        %series,
+        events = cache.events.len(),
+        docs = cache.documents.len(),
+        "Repopulated cache"
     );
 }
 
-pub async fn runner(pool: &Pool<Postgres>) {
-    let mut f1_local_cache = LocalCache::default();
-    let mut f2_local_cache = LocalCache::default();
-    let mut f3_local_cache = LocalCache::default();
+/// Scans all series every `scan_interval` until `stop` is set. Returns
+/// whether the last cycle completed without hard errors.
+pub async fn runner(
+    pool: &Pool<Postgres>,
+    client: &reqwest::Client,
+    fia: &FiaClient,
+    config: &Config,
+    stop: &AtomicBool,
+) -> bool {
+    let mut caches: Vec<(Series, LocalCache)> = config
+        .series
+        .iter()
+        .map(|series| (*series, LocalCache::default()))
+        .collect();
+    let mut consecutive_failures = 0;
 
     loop {
         let start = Utc::now();
-        println!("Scanning for documents.");
-        populate_cache(pool, &mut f1_local_cache, Series::f1).await;
-        populate_cache(pool, &mut f2_local_cache, Series::f2).await;
-        populate_cache(pool, &mut f3_local_cache, Series::f3).await;
+        info!("Scanning for documents.");
+        for (series, cache) in caches.iter_mut() {
+            populate_cache(pool, cache, *series, config.year).await;
+        }
 
+        // series are independent, so scan them side by side. the pool
+        // hands each runner its own connection.
         #[cfg(not(debug_assertions))]
+        let completed = futures::future::join_all(caches.iter_mut().map(
+            |(series, cache)| async move {
+                let start = Utc::now();
+                let mut stats = ScanStats::default();
+                let completed = f1_runner(
+                    pool, client, fia, config, stop, *series, cache, &mut stats,
+                )
+                .await;
+                info!(
+                    %series,
+                    events = stats.events,
+                    documents_found = stats.documents_found,
+                    documents_mirrored = stats.documents_mirrored,
+                    pages_uploaded = stats.pages_uploaded,
+                    errors = stats.errors,
+                    duration_ms = (Utc::now() - start).num_milliseconds(),
+                    completed,
+                    "Series scan summary"
+                );
+                completed
+            },
+        ))
+        .await
+        .into_iter()
+        .all(|completed| completed);
+        #[cfg(debug_assertions)]
+        let completed = true;
+        let runner_time = (Utc::now() - start).to_std().unwrap();
+        METRICS.finish_cycle(runner_time, completed);
+        if completed {
+            consecutive_failures = 0;
+        } else {
+            consecutive_failures += 1;
+            if consecutive_failures >= config.max_consecutive_failures {
+                error!(consecutive_failures, "Scan cycles keep failing");
+            } else {
+                warn!(consecutive_failures, "Scan cycle failed");
+            }
+        }
+        if config.run_once || stop.load(Ordering::Relaxed) {
+            return completed;
+        }
+
+        let wait = config
+            .scan_interval
+            .checked_sub(runner_time)
+            .unwrap_or(Duration::from_secs(1));
+        let wait = jittered(wait, config.scan_jitter);
+        let wake = tokio::time::Instant::now() + wait;
+        // sleep in steps so a shutdown doesn't wait out the whole interval.
+        while !stop.load(Ordering::Relaxed)
+            && tokio::time::Instant::now() < wake
         {
-            f1_runner(
-                pool,
-                YEAR as i16,
-                F1_DOCS_URL,
-                Series::f1,
-                &mut f1_local_cache,
-            )
-            .await;
-            f1_runner(
-                pool,
-                YEAR as i16,
-                F2_DOCS_URL,
-                Series::f2,
-                &mut f2_local_cache,
-            )
-            .await;
-            f1_runner(
-                pool,
-                YEAR as i16,
-                F3_DOCS_URL,
-                Series::f3,
-                &mut f3_local_cache,
-            )
-            .await;
+            tokio::time::sleep(Duration::from_secs(1)).await;
         }
-        let runner_time = (Utc::now() - start).to_std().unwrap();
+        if stop.load(Ordering::Relaxed) {
+            return completed;
+        }
+    }
+}
 
-        std::thread::sleep(
-            Duration::from_secs(180)
-                .checked_sub(runner_time)
-                .unwrap_or(Duration::from_secs(1)),
-        );
+/// Randomly stretches or shrinks `wait` by up to `jitter`.
+fn jittered(
+    wait: Duration,
+    jitter: f64,
+) -> Duration {
+    if jitter <= 0.0 {
+        return wait;
     }
+    let factor = rand::thread_rng().gen_range(1.0 - jitter..=1.0 + jitter);
+    return wait.mul_f64(factor);
 }
 
+/// Scans a series' season and mirrors new documents, returns false if the
+/// scan was cut short by an error.
 async fn f1_runner(
     pool: &Pool<Postgres>,
-    year: i16,
-    url: &str,
+    client: &reqwest::Client,
+    fia: &FiaClient,
+    config: &Config,
+    stop: &AtomicBool,
     series: Series,
     cache: &mut LocalCache,
-) {
-    let season = match get_season(url, NonZeroI16::new(year).unwrap()).await {
-        Ok(season) => season,
+    stats: &mut ScanStats,
+) -> bool {
+    if config.magick && !config.dry_run {
+        repair_incomplete_docs(pool, client, fia, config, stop, series).await;
+    }
+    if !config.dry_run {
+        retry_notifications(pool, client, series, stats).await;
+    }
+    let url = config.docs_url(series);
+    let year = NonZeroI16::new(config.year as i16).unwrap();
+    let state = match scan_state(series, pool).await {
+        Ok(state) => state,
         Err(why) => {
-            eprintln!("Error fetching: {why}");
-            return;
+            error!(%series, "Error looking up scan state: {why}");
+            stats.errors += 1;
+            None
+        },
+    };
+    // a dry run wants to see the documents, even if nothing changed.
+    let mut validators = state
+        .as_ref()
+        .filter(|_| !config.dry_run)
+        .map(|state| Validators {
+            etag: state.etag.clone(),
+            last_modified: state.last_modified.clone(),
+        })
+        .unwrap_or_default();
+    let season = match series {
+        Series::f1a => sportity::get_season(fia, &url, year).await.map(Some),
+        _ => get_season_if_modified(fia, &url, year, &mut validators).await,
+    };
+    let season = match season {
+        Ok(Some(season)) => season,
+        Ok(None) => {
+            debug!(%series, "Season page not modified");
+            let previous = state.map_or(0, |state| state.last_document_count);
+            finish_scan(pool, config, series, previous as usize, &validators)
+                .await;
+            return true;
+        },
+        Err(why) => {
+            if matches!(why, RunnerError::Parse(_)) {
+                Metrics::inc(&METRICS.parse_failures);
+            }
+            error!(%series, %url, "Error fetching season: {why}");
+            stats.errors += 1;
+            return false;
         },
     };
+    let document_count: usize =
+        season.events.iter().map(|ev| ev.documents.len()).sum();
+    // an empty season usually means the markup changed or we got blocked.
+    if document_count == 0 {
+        Metrics::inc(&METRICS.parse_failures);
+        match state.as_ref().map(|state| state.last_document_count) {
+            Some(previous) if previous > 0 => {
+                error!(
+                    %series,
+                    %url,
+                    previous,
+                    "Possible markup change for {series}, season page yielded no documents"
+                );
+                stats.errors += 1;
+                return false;
+            },
+            _ => warn!(%series, %url, "Season page yielded no documents"),
+        }
+    }
+    debug!(
+        %series,
+        events = season.events.len(),
+        documents = document_count,
+        "Parsed season"
+    );
+    // the event with the latest document is the one still getting them,
+    // older events are settled.
+    let current = season
+        .events
+        .iter()
+        .enumerate()
+        .filter_map(|(index, ev)| {
+            let latest =
+                ev.documents.iter().filter_map(|doc| parse_date(&doc.date));
+            latest.max().map(|date| (index, date))
+        })
+        .max_by_key(|(_, date)| *date)
+        .map(|(index, _)| index);
     let series_str: String = series.into();
-    for ev in season.events {
+    // documents are collected first and mirrored concurrently across events.
+    let mut pending = vec![];
+    for (index, ev) in season.events.into_iter().enumerate() {
+        // stop between events, documents check again before starting.
+        if stop.load(Ordering::Relaxed) {
+            return false;
+        }
+        stats.events += 1;
         let year: i16 = season.year.into();
+        // events of different series can share a name on the same weekend.
         let cache_event = cache.events.iter().find(|f| {
-            ev.title.as_ref().is_some_and(|t| *t == f.name)
+            f.series == series
+                && ev.title.as_ref().is_some_and(|t| *t == f.name)
                 && ev.season.is_some_and(|s| i16::from(s) == f.year as i16)
         });
 
@@ -184,7 +385,7 @@ async fn f1_runner(
             Some(db_event) => db_event.clone(),
             None => match sqlx::query_as_unchecked!(
                 Event,
-                "SELECT id as \"id?\", name, year, created, series FROM events where name = $1 AND year = $2 AND series = $3",
+                "SELECT id as \"id?\", name, year, created, series as \"series: Series\" FROM events where name = $1 AND year = $2 AND series = $3",
                 ev.title,
                 year,
                 series_str
@@ -195,11 +396,19 @@ async fn f1_runner(
                         cache.events.push(db_event.clone());
                         db_event
                     },
+                Ok(None) if config.dry_run => {
+                    info!(%series, event = ev.title, "Dry run: would create event");
+                    for doc in &ev.documents {
+                        info!(%series, title = doc.title, url = doc.url, "Dry run: would mirror doc");
+                    }
+                    continue;
+                },
                 Ok(None) => {
                     match insert_event(pool, year, &ev, series).await {
                         Err(why) => {
-                            eprintln!("Error creating event: {why}");
-                            return;
+                            error!(%series, event = ev.title, "Error creating event: {why}");
+                            stats.errors += 1;
+                            return false;
                         },
                         Ok(event) => {
                                 cache.events.push(event.clone());
@@ -208,306 +417,2270 @@ async fn f1_runner(
                     }
                 },
                 Err(why) => {
-                    eprintln!("sqlx Error: {why}");
+                    error!(%series, event = ev.title, "Error looking up event: {why}");
+                    stats.errors += 1;
                     continue;
                 }
             }
         };
-        for (i, doc) in ev.documents.iter().enumerate() {
-            if cache.documents.iter().any(|f| {
-                return f.url == *doc.url.as_ref().unwrap();
-            }) {
+        if current == Some(index) && config.reconcile_removed && !config.dry_run
+        {
+            reconcile_removed(pool, series, &db_event, &ev).await;
+        }
+        for doc in ev.documents {
+            if cache.documents.contains(&doc.url) {
                 continue;
             }
-            println!("doc not found!");
-            let (title, url, _) = (
-                doc.title.as_ref().unwrap(),
-                doc.url.as_ref().unwrap(),
-                doc.date.as_ref().unwrap(),
-            );
-            let (file, body) =
-                match download_file(url, &format!("doc_{i}")).await {
-                    Err(why) => {
-                        eprintln!("Download Error: {why}");
-                        continue;
-                    },
-                    Ok(data) => data,
-                };
+            if config.dry_run {
+                info!(
+                    %series,
+                    event = db_event.name,
+                    title = doc.title,
+                    url = doc.url,
+                    "Dry run: would mirror doc"
+                );
+                continue;
+            }
+            pending.push((db_event.clone(), doc));
+        }
+    }
 
-            let mirror_url =
-                match upload_mirror(title, &db_event.name, year, &body).await {
-                    Err(why) => {
-                        eprintln!("error uploading mirror doc:{why}");
-                        continue;
-                    },
-                    Ok(url) => url,
-                };
+    // an event can list the same document twice.
+    let mut seen = HashSet::new();
+    pending.retain(|(_, doc)| seen.insert(doc.url.clone()));
+    let claimed = Mutex::new(HashSet::new());
+    let mirrors = pending.iter().map(|(db_event, doc)| {
+        let claimed = &claimed;
+        async move {
+            let mut doc_stats = ScanStats::default();
+            let outcome = mirror_document(
+                pool,
+                client,
+                fia,
+                config,
+                stop,
+                series,
+                db_event,
+                doc,
+                claimed,
+                &mut doc_stats,
+            )
+            .await;
+            (&doc.url, outcome, doc_stats)
+        }
+    });
+    let outcomes = futures::stream::iter(mirrors)
+        .buffer_unordered(config.max_concurrent_docs)
+        .collect::<Vec<_>>()
+        .await;
+    let mut stopped = false;
+    for (url, outcome, doc_stats) in outcomes {
+        stats.add(&doc_stats);
+        match outcome {
+            DocOutcome::Known => {
+                cache.documents.insert(url.clone());
+            },
+            DocOutcome::Failed => {},
+            DocOutcome::Stopped => stopped = true,
+        }
+    }
+    let mut checked = HashSet::new();
+    for (db_event, _) in &pending {
+        if checked.insert(db_event.id) {
+            warn_doc_number_gaps(series, db_event, pool).await;
+            link_superseded(series, db_event, pool).await;
+        }
+    }
+    if let Err(why) = clear_tmp_files(&config.tmp_dir, &format!("{series}_")) {
+        error!(%series, "Couldn't clear temp files: {why}");
+        stats.errors += 1;
+    }
+    if stopped {
+        return false;
+    }
+    // documents that failed only get retried if the page is fetched again.
+    if stats.errors > 0 {
+        validators = Validators::default();
+    }
+    finish_scan(pool, config, series, document_count, &validators).await;
+    return true;
+}
 
-            let series_str: String = series.into();
-            struct Id {
-                id: i64,
-            }
-            let inserted_doc: Id = match sqlx::query_as_unchecked!(Id,
-                "INSERT INTO documents (event, url, title, series, mirror) VALUES ($1, $2, $3, $4, $5) RETURNING id",
-                    db_event.id.as_ref().unwrap(),
-                    url,
-                    title,
-                    series_str,
-                    mirror_url
-                ).fetch_one(pool).await {
-                        Err(why) => {
-                            eprintln!("Error inserting doc: {why}");
-                            continue;
-                        }
-                        Ok(data) => data
-                    };
-            println!("adding doc {title}");
-            cache.documents.push(MinDoc {
-                url: url.clone(),
-            });
-            let files =
-                match run_magick(file.to_str().unwrap(), &format!("doc_{i}")) {
-                    Err(why) => {
-                        eprintln!("error running magick: {why}");
-                        continue;
-                    },
-                    Ok(data) => data,
-                };
+/// How mirroring a single document ended.
+enum DocOutcome {
+    /// the document is stored, later scans skip it.
+    Known,
+    /// failed before it got stored, the next scan tries again.
+    Failed,
+    /// cut short by a shutdown.
+    Stopped,
+}
 
-            for (j, path) in files.iter().enumerate() {
-                let mut file = match File::open(path) {
-                    Err(why) => {
-                        eprintln!("Error opening file: {why}");
-                        continue;
-                    },
-                    Ok(data) => data,
-                };
+/// Downloads, mirrors and converts a new document of `event`, then notifies
+/// discord about it. `claimed` holds the content hashes of documents that
+/// are being mirrored concurrently, so re-published copies in the same scan
+/// aren't stored twice.
+async fn mirror_document(
+    pool: &Pool<Postgres>,
+    client: &reqwest::Client,
+    fia: &FiaClient,
+    config: &Config,
+    stop: &AtomicBool,
+    series: Series,
+    db_event: &Event,
+    doc: &ParserDocument,
+    claimed: &Mutex<HashSet<String>>,
+    stats: &mut ScanStats,
+) -> DocOutcome {
+    if stop.load(Ordering::Relaxed) {
+        return DocOutcome::Stopped;
+    }
+    let (title, url, date) = (&doc.title, &doc.url, &doc.date);
+    let year = db_event.year as i16;
+    debug!(%series, event = db_event.name, title, "New document");
+    Metrics::inc(&METRICS.documents_discovered);
+    stats.documents_found += 1;
+    let published = parse_date(date).unwrap_or_else(|| {
+        warn!(%series, title, date, "Couldn't parse publish date");
+        Utc::now()
+    });
+    // prefixed by series so clean up stays per runner, the url
+    // digest keeps documents from clobbering each other's files.
+    let tmp_name = format!(
+        "{series}_{}_{}",
+        db_event.id.unwrap(),
+        &sha256::digest(url.as_str())[..16]
+    );
+    debug!(%series, title, url, "Downloading doc");
+    let download = match download_file(
+        fia,
+        &config.tmp_dir,
+        url,
+        &tmp_name,
+        config.max_download_bytes,
+    )
+    .await
+    {
+        Err(why) => {
+            error!(%series, title, url, "Download error: {why}");
+            stats.errors += 1;
+            return DocOutcome::Failed;
+        },
+        Ok(data) => data,
+    };
 
-                // I think 10 Mb is a reasonable size, most docs will be under that.
-                let mut buf = Vec::with_capacity(1024 * 1024 * 10);
-                match file.read_to_end(&mut buf) {
-                    Err(why) => {
-                        eprintln!("Error reading file: {why}");
-                        continue;
-                    },
-                    Ok(data) => data,
-                };
-                let digest = sha256::digest(buf.as_slice());
+    // the FIA sometimes re-publishes a document under a new url.
+    if !claimed.lock().unwrap().insert(download.hash.clone()) {
+        info!(%series, title, url, "Skipping doc, identical content is being mirrored");
+        return DocOutcome::Known;
+    }
+    match document_hash_exists(&download.hash, series, pool).await {
+        Ok(false) => {},
+        Ok(true) => {
+            info!(%series, title, url, "Skipping doc, identical content exists");
+            return DocOutcome::Known;
+        },
+        Err(why) => {
+            error!(%series, title, "Error checking document hash: {why}");
+            stats.errors += 1;
+            return DocOutcome::Failed;
+        },
+    }
+
+    debug!(%series, title, bytes = download.body.len(), "Uploading mirror");
+    let mirror_url = match upload_mirror(
+        client,
+        &config.storage,
+        series,
+        title,
+        &db_event.name,
+        year,
+        &download,
+    )
+    .await
+    {
+        Err(why) => {
+            error!(%series, title, "Error uploading mirror: {why}");
+            stats.errors += 1;
+            Metrics::inc(&METRICS.upload_failures);
+            return DocOutcome::Failed;
+        },
+        Ok(url) => url,
+    };
 
-                let url = format!(
-                    "https://fia.ort.dev/{}/{}/{}-{}.jpg",
+    let series_str: String = series.into();
+    let doc_type = DocumentType::classify(title);
+    let doc_type_str: String = doc_type.into();
+    let source = Source::from(series);
+    let source_str: String = source.into();
+    let media_type_str: String = download.media_type.into();
+    let number = doc_number(title);
+    struct Id {
+        id: i64,
+    }
+    let inserted_doc: Id = match sqlx::query_as_unchecked!(Id,
+        "INSERT INTO documents (event, url, title, series, mirror, hash, published, doc_type, source, media_type, parser_version, doc_number, original_filename) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13) RETURNING id",
+            db_event.id.as_ref().unwrap(),
+            url,
+            title,
+            series_str,
+            mirror_url,
+            download.hash,
+            published,
+            doc_type_str,
+            source_str,
+            media_type_str,
+            PARSER_VERSION,
+            number,
+            download.original_filename
+        ).fetch_one(pool).await {
+        Err(why) => {
+            error!(%series, title, "Error inserting doc: {why}");
+            stats.errors += 1;
+            // another series can publish the same file under an event of
+            // the same name, only delete the mirror if nothing uses it. a
+            // kept one gets overwritten by the retry under the same key.
+            if let Ok(false) = mirror_referenced(&mirror_url, pool).await {
+                let key = mirror_key(
+                    title,
+                    &db_event.name,
                     year,
-                    urlencoding::encode(ev.title.as_ref().unwrap()),
-                    inserted_doc.id,
-                    j
-                );
-                let now = Utc::now();
-                let mut headers = reqwest::header::HeaderMap::new();
-                headers.insert("x-amz-content-sha256", digest.parse().unwrap());
-                headers.insert("x-amz-acl", "public-read".parse().unwrap());
-                headers.insert(
-                    "X-Amz-Date",
-                    now.format("%Y%m%dT%H%M%SZ").to_string().parse().unwrap(),
-                );
-                headers.insert("host", "fia.ort.dev".parse().unwrap());
-                let secret = std::env::var("S3_SECRET_KEY").unwrap();
-                let access = std::env::var("S3_ACCESS_KEY").unwrap();
-                let sign = AwsSign::new(
-                    "PUT",
-                    &url,
-                    &now,
-                    &headers,
-                    "us-east-1",
-                    &access,
-                    &secret,
-                    "s3",
-                    Some(&digest),
+                    &download.hash,
+                    download.media_type,
                 );
-                let signature = sign.sign();
-                headers.insert(AUTHORIZATION, signature.parse().unwrap());
-                headers.insert(CONTENT_TYPE, "image/jpeg".parse().unwrap());
-                let client = reqwest::Client::new();
-                match client.put(&url).headers(headers).body(buf).send().await {
-                    Ok(data) => match data.error_for_status() {
-                        Err(why) => {
-                            eprintln!("Uploade Error: {why}");
-                        },
-                        Ok(_) => {
-                            if let Err(why) = insert_image(
-                                inserted_doc.id,
-                                j as i32,
-                                url,
-                                pool,
-                            )
-                            .await
-                            {
-                                eprintln!("Error inserting: {why}")
-                            }
-                        },
-                    },
-                    Err(why) => {
-                        eprintln!("Error: {why}");
-                    },
+                let storage = storage::open(client, &config.storage, series);
+                if let Err(why) = storage.delete(&key).await {
+                    warn!(%series, title, key, "Couldn't delete orphaned mirror: {why}");
                 }
             }
-            match mark_doc_done(inserted_doc.id, pool).await {
-                Ok(_) => {},
-                Err(why) => {
-                    println!("Error marking doc done: {why}");
-                },
-            }
+            return DocOutcome::Failed;
+        },
+        Ok(data) => data,
+    };
+    info!(%series, event = db_event.name, title, "Added doc");
+    Metrics::inc(&METRICS.documents_mirrored);
+    stats.documents_mirrored += 1;
+    // without magick we still mirror the document, just no pages.
+    let mut page_count = None;
+    if config.magick {
+        let convert = convert_options(config, doc_type);
+        debug!(%series, title, "Converting doc");
+        let files = match run_magick(
+            &config.tmp_dir,
+            download.path.to_str().unwrap(),
+            &tmp_name,
+            &convert,
+        )
+        .await
+        {
+            Err(why) => {
+                error!(%series, title, "Error running magick: {why}");
+                stats.errors += 1;
+                return DocOutcome::Known;
+            },
+            Ok(data) => data,
+        };
+        let count = files.len() as i32;
+        match set_page_count(inserted_doc.id, count, pool).await {
+            Ok(_) => page_count = Some(count),
+            Err(why) => {
+                error!(%series, title, "Error setting page count: {why}");
+                stats.errors += 1;
+            },
+        }
+
+        // pages skipped on shutdown get picked up by the repair.
+        let uploads = files
+            .iter()
+            .enumerate()
+            .filter(|_| !stop.load(Ordering::Relaxed))
+            .map(|(j, path)| {
+                upload_page(
+                    client,
+                    &config.storage,
+                    path,
+                    j,
+                    db_event,
+                    inserted_doc.id,
+                    convert.format,
+                    None,
+                    pool,
+                )
+            });
+        let uploaded = futures::stream::iter(uploads)
+            .buffer_unordered(PAGE_UPLOAD_CONCURRENCY)
+            .collect::<Vec<Option<PageImage>>>()
+            .await;
+        let attempted = uploaded.len();
+        let images: Vec<PageImage> = uploaded.into_iter().flatten().collect();
+        stats.errors += attempted - images.len();
+        // recorded in one go, pages that didn't make it get repaired.
+        match insert_images(inserted_doc.id, &images, pool).await {
+            Ok(()) => stats.pages_uploaded += images.len(),
+            Err(why) => {
+                error!(%series, title, "Error inserting images: {why}");
+                stats.errors += images.len();
+            },
+        }
+        if stop.load(Ordering::Relaxed) {
+            return DocOutcome::Stopped;
         }
-        if let Err(why) = clear_tmp_dir() {
-            eprintln!("couldn't clear temp dir: {why}");
+    }
+
+    match mark_doc_done(inserted_doc.id, pool).await {
+        Ok(_) => {},
+        Err(why) => {
+            error!(%series, title, "Error marking doc done: {why}");
+            stats.errors += 1;
+        },
+    }
+
+    let Ok(webhook) = std::env::var("DISCORD_WEBHOOK_URL") else {
+        return DocOutcome::Known;
+    };
+    let document = Document {
+        id: Some(inserted_doc.id),
+        event: db_event.id.unwrap(),
+        title: title.clone(),
+        series,
+        source,
+        created: Utc::now(),
+        published,
+        url: url.clone(),
+        mirror: mirror_url,
+        notified: false,
+        hash: Some(download.hash),
+        removed: None,
+        page_count,
+        doc_type,
+        media_type: download.media_type,
+        doc_number: number,
+        original_filename: download.original_filename,
+        supersedes: None,
+    };
+    match claim_notification(inserted_doc.id, pool).await {
+        Ok(true) => {},
+        Ok(false) => return DocOutcome::Known,
+        Err(why) => {
+            error!(%series, title, "Error claiming notification: {why}");
+            stats.errors += 1;
+            return DocOutcome::Known;
+        },
+    }
+    if let Err(why) =
+        notify_discord(client, &webhook, db_event, &document).await
+    {
+        warn!(%series, title, "Error notifying discord: {why}");
+        if let Err(why) = release_notification(inserted_doc.id, pool).await {
+            error!(%series, title, "Error releasing notification: {why}");
+            stats.errors += 1;
         }
     }
+    return DocOutcome::Known;
 }
 
-async fn mark_doc_done(
-    doc_id: i64,
+/// Links revised documents of an event to the ones they replace.
+async fn link_superseded(
+    series: Series,
+    event: &Event,
     pool: &Pool<Postgres>,
-) -> Result<i64, Box<dyn Error>> {
-    struct Id {
+) {
+    struct Row {
         id: i64,
+        title: String,
+        supersedes: Option<i64>,
     }
-    let id = sqlx::query_as!(
-        Id,
-        "UPDATE documents SET done = 1 WHERE id = $1 RETURNING id",
-        doc_id
+    let docs = match sqlx::query_as_unchecked!(
+        Row,
+        "SELECT id, title, supersedes FROM documents WHERE event = $1 ORDER BY id",
+        event.id
     )
-    .fetch_one(pool)
-    .await?;
-
-    Ok(id.id)
+    .fetch_all(pool)
+    .await
+    {
+        Ok(docs) => docs,
+        Err(why) => {
+            error!(%series, event = event.name, "Error fetching docs: {why}");
+            return;
+        },
+    };
+    for (index, doc) in docs.iter().enumerate() {
+        if doc.supersedes.is_some() || !is_revision(&doc.title) {
+            continue;
+        }
+        // the latest earlier match, so revisions of revisions chain up.
+        let original = docs[..index]
+            .iter()
+            .rev()
+            .find(|other| revises(&doc.title, &other.title));
+        let Some(original) = original else {
+            continue;
+        };
+        if let Err(why) = sqlx::query!(
+            "UPDATE documents SET supersedes = $1 WHERE id = $2",
+            original.id,
+            doc.id
+        )
+        .execute(pool)
+        .await
+        {
+            error!(%series, title = doc.title, "Error linking superseded doc: {why}");
+            continue;
+        }
+        info!(
+            %series,
+            event = event.name,
+            title = doc.title,
+            supersedes = original.title,
+            "Linked revised document"
+        );
+    }
 }
 
-async fn insert_image(
-    doc_id: i64,
-    page: i32,
-    url: String,
+/// Warns about numbers missing between an event's documents, a gap means
+/// the parser or the FIA skipped one.
+async fn warn_doc_number_gaps(
+    series: Series,
+    event: &Event,
     pool: &Pool<Postgres>,
-) -> Result<(), Box<dyn Error>> {
-    sqlx::query!(
-        "INSERT INTO images (document, url, pagenum) VALUES ($1, $2, $3)",
-        doc_id,
-        url,
-        page
+) {
+    let numbers = match sqlx::query_scalar!(
+        r#"SELECT DISTINCT doc_number AS "doc_number!" FROM documents WHERE event = $1 AND doc_number IS NOT NULL ORDER BY 1"#,
+        event.id
     )
-    .execute(pool)
-    .await?;
-
-    Ok(())
-}
-
-async fn upload_mirror(
-    title: &str,
-    event: &str,
-    year: i16,
-    content: &Vec<u8>,
-) -> Result<String, Box<dyn Error>> {
-    let now = Utc::now();
-    let title = urlencoding::encode(title);
-    let url = format!("https://fia.ort.dev/mirror/{year}/{event}/{title}.pdf");
-    let digest = sha256::digest(content.as_slice());
-    let mut headers = reqwest::header::HeaderMap::new();
-    headers.insert("x-amz-content-sha256", digest.parse().unwrap());
-    headers.insert("x-amz-acl", "public-read".parse().unwrap());
-    headers.insert(
-        "X-Amz-Date",
-        now.format("%Y%m%dT%H%M%SZ").to_string().parse().unwrap(),
-    );
-    headers.insert("host", "fia.ort.dev".parse().unwrap());
-    let secret = std::env::var("S3_SECRET_KEY").unwrap();
-    let access = std::env::var("S3_ACCESS_KEY").unwrap();
-    let sign = AwsSign::new(
-        "PUT",
-        &url,
-        &now,
-        &headers,
-        "us-east-1",
-        &access,
-        &secret,
-        "s3",
-        Some(&digest),
-    );
-    let signature = sign.sign();
-    headers.insert(AUTHORIZATION, signature.parse().unwrap());
-    headers.insert(CONTENT_TYPE, "application/pdf".parse().unwrap());
-
-    let client = reqwest::Client::new();
-    let t = client
-        .put(url)
-        .headers(headers)
-        .body(content.to_owned())
-        .send()
-        .await?;
-    let url = t.url().to_string();
-    t.error_for_status()?;
-    Ok(url)
-}
-
-async fn download_file(
-    url: &str,
-    name: &str,
-) -> Result<(PathBuf, Vec<u8>), Box<dyn Error>> {
-    let request = reqwest::get(url).await?;
-    let mut file = File::create(format!("./tmp/{name}.pdf"))?;
-    let body = request.bytes().await?;
-    file.set_len(body.len() as u64)?;
-    file.write_all(&body)?;
-    let path = PathBuf::from_str(&format!("./tmp/{name}.pdf"))?;
-    // ensure we're actually pointing to a legit file.
-    path.try_exists()?;
-    Ok((path, body.to_vec()))
+    .fetch_all(pool)
+    .await
+    {
+        Ok(numbers) => numbers,
+        Err(why) => {
+            error!(%series, event = event.name, "Error fetching doc numbers: {why}");
+            return;
+        },
+    };
+    let missing: Vec<i32> =
+        numbers.windows(2).flat_map(|pair| pair[0] + 1..pair[1]).collect();
+    if !missing.is_empty() {
+        warn!(%series, event = event.name, ?missing, "Gap in document numbers");
+    }
 }
 
-async fn insert_event(
+/// Records a completed scan of `series`.
+async fn finish_scan(
     pool: &Pool<Postgres>,
-    year: i16,
-    event: &ParserEvent,
+    config: &Config,
     series: Series,
-) -> Result<Event, Box<dyn Error>> {
-    struct Id {
-        id: i64,
+    document_count: usize,
+    validators: &Validators,
+) {
+    if !config.dry_run {
+        if let Err(why) =
+            record_scan_state(series, document_count as i32, validators, pool)
+                .await
+        {
+            error!(%series, "Error recording scan state: {why}");
+        }
     }
-
-    let mut db_event = Event {
-        id: None,
-        series,
-        year: year as i32,
-        name: event.title.as_ref().unwrap().clone(),
-        created: Utc::now(),
-    };
-    let series: String = db_event.series.into();
-    let res: Id = sqlx::query_as_unchecked!(Id, "INSERT INTO events (series, year, name, created, current, new) VALUES ($1, $2, $3, $4, 0, 1) RETURNING id",
-    series,
-    db_event.year,
-    db_event.name,
-    db_event.created).fetch_one(pool).await?;
-    db_event.id = Some(res.id);
-    Ok(db_event)
+    METRICS.record_scan(series, document_count as u64);
+    info!(%series, documents = document_count, "Finished scanning series");
 }
 
-async fn get_season(
-    url: &str,
-    year: NonZeroI16,
-) -> Result<super::parser::Season, Box<dyn Error>> {
-    let test = reqwest::get(url).await?;
-
-    let bytes = test.text().await?;
-
-    let mut tendril = ByteTendril::new();
-    let _ = bytes.as_bytes().read_to_tendril(&mut tendril);
-    let mut input = BufferQueue::new();
-    input.push_back(tendril.try_reinterpret().unwrap());
-    let mut parser_season = super::parser::Season {
-        year,
-        events: vec![],
+/// Re-converts documents that have fewer images than pages, e.g. when an
+/// upload failed or we got killed halfway, and uploads the missing pages.
+async fn repair_incomplete_docs(
+    pool: &Pool<Postgres>,
+    client: &reqwest::Client,
+    fia: &FiaClient,
+    config: &Config,
+    stop: &AtomicBool,
+    series: Series,
+) {
+    let docs = match incomplete_documents(series, pool).await {
+        Ok(docs) => docs,
+        Err(why) => {
+            error!(%series, "Error looking up incomplete docs: {why}");
+            return;
+        },
     };
-    let sink = HTMLParser::new(&mut parser_season);
-    let opts = TokenizerOpts::default();
-    let mut tok = Tokenizer::new(sink, opts);
-    let _ = tok.feed(&mut input);
-    tok.end();
-    Ok(parser_season)
+    for (doc, event) in docs {
+        if stop.load(Ordering::Relaxed) {
+            break;
+        }
+        let existing = match image_pages(doc.id, pool).await {
+            Ok(pages) => pages,
+            Err(why) => {
+                error!(%series, doc_id = doc.id, "Error fetching pages: {why}");
+                continue;
+            },
+        };
+        info!(
+            %series,
+            doc_id = doc.id,
+            pages = existing.len(),
+            "Repairing incomplete doc"
+        );
+        let tmp_name = format!("{series}_repair_{}", doc.id);
+        reconvert_doc(
+            pool, client, fia, config, stop, &event, &doc, &existing, &tmp_name,
+        )
+        .await;
+    }
+    if let Err(why) =
+        clear_tmp_files(&config.tmp_dir, &format!("{series}_repair_"))
+    {
+        error!(%series, "Couldn't clear temp files: {why}");
+    }
+}
+
+/// The configured convert options, with diagrams rendered as png.
+fn convert_options(
+    config: &Config,
+    doc_type: DocumentType,
+) -> ConvertOptions {
+    if doc_type.is_line_art() {
+        return ConvertOptions {
+            format: ImageFormat::Png,
+            ..config.convert
+        };
+    }
+    return config.convert;
+}
+
+/// Renders a mirrored document again and uploads its pages, except the
+/// ones in `skip`. Returns whether all pages got uploaded.
+async fn reconvert_doc(
+    pool: &Pool<Postgres>,
+    client: &reqwest::Client,
+    fia: &FiaClient,
+    config: &Config,
+    stop: &AtomicBool,
+    event: &Event,
+    doc: &StoredDoc,
+    skip: &[i32],
+    tmp_name: &str,
+) -> bool {
+    let series = event.series;
+    let mirrored = download_from_mirror(
+        client,
+        &config.tmp_dir,
+        &doc.mirror,
+        tmp_name,
+        config.max_download_bytes,
+    )
+    .await;
+    let download = match mirrored {
+        Ok(data) => data,
+        Err(why) => {
+            warn!(%series, doc_id = doc.id, "Mirror download error, trying the FIA: {why}");
+            match download_file(
+                fia,
+                &config.tmp_dir,
+                &doc.url,
+                tmp_name,
+                config.max_download_bytes,
+            )
+            .await
+            {
+                Ok(data) => data,
+                Err(why) => {
+                    error!(%series, doc_id = doc.id, "Download error: {why}");
+                    return false;
+                },
+            }
+        },
+    };
+    // the stored document is canonical, don't render pages of a file
+    // that got swapped out since.
+    if doc.hash.as_ref().is_some_and(|hash| *hash != download.hash) {
+        warn!(
+            %series,
+            doc_id = doc.id,
+            url = doc.url,
+            "Document changed since mirroring, skipping"
+        );
+        return false;
+    }
+    let convert = convert_options(config, DocumentType::classify(&doc.title));
+    let files = match run_magick(
+        &config.tmp_dir,
+        download.path.to_str().unwrap(),
+        tmp_name,
+        &convert,
+    )
+    .await
+    {
+        Ok(data) => data,
+        Err(why) => {
+            error!(%series, doc_id = doc.id, "Error running magick: {why}");
+            return false;
+        },
+    };
+    let page_count = files.len() as i32;
+    if let Err(why) = set_page_count(doc.id, page_count, pool).await {
+        error!(%series, doc_id = doc.id, "Error setting page count: {why}");
+    }
+    // a shorter render would leave stale pages behind.
+    if let Err(why) = delete_images_from(doc.id, page_count, pool).await {
+        error!(%series, doc_id = doc.id, "Error deleting stale pages: {why}");
+    }
+    let uploads = files
+        .iter()
+        .enumerate()
+        .filter(|(j, _)| {
+            !skip.contains(&(*j as i32)) && !stop.load(Ordering::Relaxed)
+        })
+        .map(|(j, path)| {
+            store_page(
+                client,
+                &config.storage,
+                path,
+                j,
+                event,
+                doc.id,
+                convert.format,
+                pool,
+            )
+        });
+    futures::stream::iter(uploads)
+        .buffer_unordered(PAGE_UPLOAD_CONCURRENCY)
+        .collect::<Vec<bool>>()
+        .await;
+    return !stop.load(Ordering::Relaxed);
+}
+
+/// Re-parses the season of every series and updates the title and type of
+/// documents stored by an older parser, nothing gets downloaded or
+/// converted again.
+pub async fn refresh_metadata(
+    pool: &Pool<Postgres>,
+    fia: &FiaClient,
+    config: &Config,
+) -> bool {
+    let mut completed = true;
+    let year = NonZeroI16::new(config.year as i16).unwrap();
+    for series in config.series.iter().copied() {
+        let url = config.docs_url(series);
+        let season = match series {
+            Series::f1a => sportity::get_season(fia, &url, year).await,
+            _ => get_season(fia, &url, year).await,
+        };
+        let season = match season {
+            Ok(season) => season,
+            Err(why) => {
+                error!(%series, %url, "Error fetching season: {why}");
+                completed = false;
+                continue;
+            },
+        };
+        let series_str: String = series.into();
+        let mut refreshed = 0;
+        for doc in season.events.iter().flat_map(|ev| &ev.documents) {
+            let doc_type: String = DocumentType::classify(&doc.title).into();
+            match sqlx::query!(
+                "UPDATE documents SET title = $1, doc_type = $2, doc_number = $3, parser_version = $4 WHERE url = $5 AND series = $6 AND parser_version < $4",
+                doc.title,
+                doc_type,
+                doc_number(&doc.title),
+                PARSER_VERSION,
+                doc.url,
+                series_str
+            )
+            .execute(pool)
+            .await
+            {
+                Ok(result) => refreshed += result.rows_affected(),
+                Err(why) => {
+                    error!(%series, url = doc.url, "Error refreshing doc: {why}");
+                    completed = false;
+                },
+            }
+        }
+        info!(%series, refreshed, "Refreshed document metadata");
+    }
+    return completed;
+}
+
+/// Sends the notification of a document again, even if it was sent before.
+pub async fn renotify(
+    pool: &Pool<Postgres>,
+    client: &reqwest::Client,
+    webhook: &str,
+    doc_id: i64,
+) -> Result<(), RunnerError> {
+    let document = stored_document(doc_id, pool).await?;
+    let event = get_event(document.event, pool).await?;
+    notify_discord(client, webhook, &event, &document).await?;
+    mark_doc_notified(doc_id, pool).await?;
+    return Ok(());
+}
+
+/// Sends the notifications that failed in an earlier cycle. Only recent
+/// documents are retried, so enabling the webhook doesn't announce the
+/// whole archive.
+async fn retry_notifications(
+    pool: &Pool<Postgres>,
+    client: &reqwest::Client,
+    series: Series,
+    stats: &mut ScanStats,
+) {
+    let Ok(webhook) = std::env::var("DISCORD_WEBHOOK_URL") else {
+        return;
+    };
+    let series_str: String = series.into();
+    let ids = match sqlx::query_scalar!(
+        "SELECT id FROM documents WHERE series = $1 AND notified = false AND done = 1 AND removed IS NULL AND created > now() - interval '1 day' ORDER BY id",
+        series_str
+    )
+    .fetch_all(pool)
+    .await
+    {
+        Ok(ids) => ids,
+        Err(why) => {
+            error!(%series, "Error looking up unsent notifications: {why}");
+            stats.errors += 1;
+            return;
+        },
+    };
+    for doc_id in ids {
+        match claim_notification(doc_id, pool).await {
+            Ok(true) => {},
+            Ok(false) => continue,
+            Err(why) => {
+                error!(%series, doc_id, "Error claiming notification: {why}");
+                stats.errors += 1;
+                continue;
+            },
+        }
+        let sent = match stored_document(doc_id, pool).await {
+            Ok(document) => match get_event(document.event, pool).await {
+                Ok(event) => {
+                    notify_discord(client, &webhook, &event, &document).await
+                },
+                Err(why) => Err(why),
+            },
+            Err(why) => Err(why),
+        };
+        match sent {
+            Ok(()) => info!(%series, doc_id, "Sent delayed notification"),
+            Err(why) => {
+                warn!(%series, doc_id, "Error notifying discord: {why}");
+                if let Err(why) = release_notification(doc_id, pool).await {
+                    error!(%series, doc_id, "Error releasing notification: {why}");
+                    stats.errors += 1;
+                }
+            },
+        }
+    }
+}
+
+/// Loads a document with the metadata it was stored with.
+async fn stored_document(
+    doc_id: i64,
+    pool: &Pool<Postgres>,
+) -> Result<Document, RunnerError> {
+    struct Row {
+        id: i64,
+        event: i64,
+        title: String,
+        series: Series,
+        source: String,
+        created: DateTime<Utc>,
+        published: DateTime<Utc>,
+        url: String,
+        mirror: String,
+        notified: bool,
+        hash: Option<String>,
+        removed: Option<DateTime<Utc>>,
+        page_count: Option<i32>,
+        doc_type: String,
+        media_type: String,
+        doc_number: Option<i32>,
+        original_filename: Option<String>,
+        supersedes: Option<i64>,
+    }
+    let row = sqlx::query_as_unchecked!(
+        Row,
+        r#"SELECT id, event, title, series as "series: Series", source, created, published, url, mirror, notified, hash, removed, page_count, doc_type, media_type, doc_number, original_filename, supersedes FROM documents WHERE id = $1"#,
+        doc_id
+    )
+    .fetch_one(pool)
+    .await?;
+    return Ok(Document {
+        id: Some(row.id),
+        event: row.event,
+        title: row.title,
+        series: row.series,
+        source: row.source.parse().unwrap_or(Source::from(row.series)),
+        created: row.created,
+        published: row.published,
+        url: row.url,
+        mirror: row.mirror,
+        notified: row.notified,
+        hash: row.hash,
+        removed: row.removed,
+        page_count: row.page_count,
+        doc_type: row.doc_type.parse().unwrap_or(DocumentType::Other),
+        media_type: row.media_type.parse().unwrap_or(MediaType::Pdf),
+        doc_number: row.doc_number,
+        original_filename: row.original_filename,
+        supersedes: row.supersedes,
+    });
+}
+
+/// Re-renders every document of an event at the current convert options,
+/// overwriting the existing page images.
+pub async fn reprocess_event(
+    pool: &Pool<Postgres>,
+    client: &reqwest::Client,
+    fia: &FiaClient,
+    config: &Config,
+    stop: &AtomicBool,
+    event_id: i64,
+) -> bool {
+    let event = match get_event(event_id, pool).await {
+        Ok(event) => event,
+        Err(why) => {
+            error!(event_id, "Error fetching event: {why}");
+            return false;
+        },
+    };
+    let docs = match event_documents(event_id, pool).await {
+        Ok(docs) => docs,
+        Err(why) => {
+            error!(event_id, "Error fetching event docs: {why}");
+            return false;
+        },
+    };
+    info!(
+        event_id,
+        event = event.name,
+        docs = docs.len(),
+        "Reprocessing event"
+    );
+    let mut completed = true;
+    for doc in docs {
+        if stop.load(Ordering::Relaxed) {
+            return false;
+        }
+        let tmp_name = format!("{}_reprocess_{}", event.series, doc.id);
+        completed &= reconvert_doc(
+            pool,
+            client,
+            fia,
+            config,
+            stop,
+            &event,
+            &doc,
+            &[],
+            &tmp_name,
+        )
+        .await;
+    }
+    if let Err(why) = clear_tmp_files(
+        &config.tmp_dir,
+        &format!("{}_reprocess_", event.series),
+    ) {
+        error!(event_id, "Couldn't clear temp files: {why}");
+    }
+    return completed;
+}
+
+/// Downloads a document from the FIA again and replaces its mirror, pages,
+/// hash and page count, for when a mirror got corrupted or a conversion
+/// went wrong. The new objects get keys of their own and replace the old
+/// ones in a single transaction, so a failure keeps the previous state.
+pub async fn remirror(
+    pool: &Pool<Postgres>,
+    client: &reqwest::Client,
+    fia: &FiaClient,
+    config: &Config,
+    doc_id: i64,
+) -> Result<(), RunnerError> {
+    // subcommands don't go through the startup that creates it.
+    create_tmp_dir(&config.tmp_dir)?;
+    let tmp_name = format!("remirror_{doc_id}");
+    let result =
+        remirror_doc(pool, client, fia, config, doc_id, &tmp_name).await;
+    if let Err(why) = clear_tmp_files(&config.tmp_dir, &tmp_name) {
+        warn!(doc_id, "Couldn't clear temp files: {why}");
+    }
+    return result;
+}
+
+async fn remirror_doc(
+    pool: &Pool<Postgres>,
+    client: &reqwest::Client,
+    fia: &FiaClient,
+    config: &Config,
+    doc_id: i64,
+    tmp_name: &str,
+) -> Result<(), RunnerError> {
+    struct Row {
+        event: i64,
+        title: String,
+        url: String,
+        mirror: String,
+        hash: Option<String>,
+    }
+    let row = sqlx::query_as_unchecked!(
+        Row,
+        "SELECT event, title, url, mirror, hash FROM documents WHERE id = $1",
+        doc_id
+    )
+    .fetch_one(pool)
+    .await?;
+    let event = get_event(row.event, pool).await?;
+    let series = event.series;
+    info!(%series, doc_id, url = row.url, "Re-mirroring doc");
+
+    // the FIA is the source here, the mirror is what might be broken.
+    let download = download_file(
+        fia,
+        &config.tmp_dir,
+        &row.url,
+        tmp_name,
+        config.max_download_bytes,
+    )
+    .await?;
+    if row.hash.as_ref().is_some_and(|hash| *hash != download.hash) {
+        warn!(%series, doc_id, "Document changed since mirroring, replacing it");
+    }
+    // everything goes to new keys, the mirror and pages in use stay
+    // untouched until the transaction swaps them out.
+    let revision = Utc::now().format("%Y%m%d%H%M%S").to_string();
+    let key = revised_key(
+        &mirror_key(
+            &row.title,
+            &event.name,
+            event.year as i16,
+            &download.hash,
+            download.media_type,
+        ),
+        &revision,
+    );
+    let mirror = storage::open(client, &config.storage, series)
+        .put(&key, &download.body, download.media_type.content_type())
+        .await?;
+
+    let mut new_pages: Vec<String> = vec![];
+    let swapped = async {
+        // without magick the existing pages are kept.
+        let mut pages = None;
+        if config.magick {
+            let convert =
+                convert_options(config, DocumentType::classify(&row.title));
+            let files = run_magick(
+                &config.tmp_dir,
+                download.path.to_str().unwrap(),
+                tmp_name,
+                &convert,
+            )
+            .await?;
+            let uploads = files.iter().enumerate().map(|(j, path)| {
+                upload_page(
+                    client,
+                    &config.storage,
+                    path,
+                    j,
+                    &event,
+                    doc_id,
+                    convert.format,
+                    Some(revision.as_str()),
+                    pool,
+                )
+            });
+            let uploaded = futures::stream::iter(uploads)
+                .buffer_unordered(PAGE_UPLOAD_CONCURRENCY)
+                .collect::<Vec<Option<PageImage>>>()
+                .await;
+            new_pages.extend(
+                uploaded.iter().flatten().map(|image| image.url.clone()),
+            );
+            let images = uploaded
+                .into_iter()
+                .collect::<Option<Vec<PageImage>>>()
+                .ok_or_else(|| {
+                    RunnerError::Content(format!(
+                        "not all of {} pages uploaded",
+                        files.len()
+                    ))
+                })?;
+            pages = Some(images);
+        }
+
+        let old_pages = match pages {
+            Some(_) => {
+                sqlx::query_scalar!(
+                    "SELECT url FROM images WHERE document = $1",
+                    doc_id
+                )
+                .fetch_all(pool)
+                .await?
+            },
+            None => vec![],
+        };
+        let media_type: String = download.media_type.into();
+        let page_count = pages.as_ref().map(|images| images.len() as i32);
+        let mut tx = pool.begin().await?;
+        sqlx::query!(
+            "UPDATE documents SET mirror = $2, hash = $3, media_type = $4, original_filename = COALESCE($5, original_filename), page_count = COALESCE($6, page_count) WHERE id = $1",
+            doc_id,
+            mirror,
+            download.hash,
+            media_type,
+            download.original_filename,
+            page_count
+        )
+        .execute(&mut *tx)
+        .await?;
+        if let Some(images) = &pages {
+            delete_images_from(doc_id, 0, &mut *tx).await?;
+            insert_images(doc_id, images, &mut *tx).await?;
+        }
+        tx.commit().await?;
+        Ok::<_, RunnerError>((page_count, old_pages))
+    }
+    .await;
+    let (page_count, old_pages) = match swapped {
+        Ok(swapped) => swapped,
+        Err(why) => {
+            // nothing references the new objects, except content addressed
+            // pages other documents might share.
+            delete_object(client, &config.storage, series, &mirror).await;
+            if !config.storage.content_addressed_images {
+                for url in &new_pages {
+                    delete_object(client, &config.storage, series, url).await;
+                }
+            }
+            return Err(why);
+        },
+    };
+    info!(%series, doc_id, pages = page_count, "Re-mirrored doc");
+
+    // the replaced objects, unless another document still uses them.
+    match mirror_referenced(&row.mirror, pool).await {
+        Ok(false) => {
+            delete_object(client, &config.storage, series, &row.mirror).await
+        },
+        Ok(true) => {},
+        Err(why) => {
+            warn!(%series, doc_id, "Error checking old mirror: {why}");
+        },
+    }
+    for url in old_pages.iter().filter(|url| !new_pages.contains(url)) {
+        match image_referenced(url, pool).await {
+            Ok(false) => {
+                delete_object(client, &config.storage, series, url).await
+            },
+            Ok(true) => {},
+            Err(why) => {
+                warn!(%series, doc_id, url, "Error checking old page: {why}");
+            },
+        }
+    }
+    return Ok(());
+}
+
+/// Deletes the object behind a public `url`, urls outside the configured
+/// storage are left alone.
+async fn delete_object(
+    client: &reqwest::Client,
+    storage: &StorageConfig,
+    series: Series,
+    url: &str,
+) {
+    let base = storage.public(series, "");
+    let Some(key) = url.strip_prefix(&base) else {
+        debug!(%series, url, "Object isn't in this storage, keeping it");
+        return;
+    };
+    let deleted = storage::open(client, storage, series).delete(key).await;
+    if let Err(why) = deleted {
+        warn!(%series, key, "Couldn't delete replaced object: {why}");
+    }
+}
+
+/// Flags documents of `event` that no longer show up on the FIA page.
+async fn reconcile_removed(
+    pool: &Pool<Postgres>,
+    series: Series,
+    event: &Event,
+    parsed: &ParserEvent,
+) {
+    // an empty event is far more likely a parse failure than a mass removal.
+    if parsed.documents.is_empty() {
+        return;
+    }
+    // a skipped document's url is missing below, its row would get flagged.
+    if parsed.incomplete > 0 {
+        warn!(
+            %series,
+            event = event.name,
+            incomplete = parsed.incomplete,
+            "Skipping removal check, the event has incomplete docs"
+        );
+        return;
+    }
+    let urls: Vec<String> =
+        parsed.documents.iter().map(|doc| doc.url.clone()).collect();
+    match mark_removed_documents(event.id.unwrap(), &urls, pool).await {
+        Ok(0) => {},
+        Ok(removed) => {
+            warn!(%series, event = event.name, removed, "Flagged removed docs");
+        },
+        Err(why) => {
+            error!(%series, event = event.name, "Error flagging removed docs: {why}");
+        },
+    }
+}
+
+/// An uploaded page image, not yet recorded in the database.
+struct PageImage {
+    page: i32,
+    url: String,
+    hash: String,
+    phash: Option<String>,
+}
+
+/// Uploads a page image, returns none if it failed. A `revision` keeps the
+/// upload from overwriting the page in use.
+async fn upload_page(
+    client: &reqwest::Client,
+    storage: &StorageConfig,
+    path: &Path,
+    page: usize,
+    event: &Event,
+    doc_id: i64,
+    format: ImageFormat,
+    revision: Option<&str>,
+    pool: &Pool<Postgres>,
+) -> Option<PageImage> {
+    let buf = match tokio::fs::read(path).await {
+        Err(why) => {
+            error!(doc_id, page, "Error reading page: {why}");
+            return None;
+        },
+        Ok(data) => data,
+    };
+    let digest = sha256::digest(buf.as_slice());
+    #[cfg(feature = "phash")]
+    let phash = crate::middleware::phash::page_hash(&buf).await;
+    #[cfg(not(feature = "phash"))]
+    let phash: Option<String> = None;
+
+    if storage.content_addressed_images {
+        // identical pages of other documents are already in the bucket.
+        match image_url_for_hash(&digest, pool).await {
+            Ok(Some(url)) => {
+                return Some(PageImage {
+                    page: page as i32,
+                    url,
+                    hash: digest,
+                    phash,
+                });
+            },
+            Ok(None) => {},
+            Err(why) => {
+                warn!(doc_id, page, "Error looking up page hash: {why}")
+            },
+        }
+    }
+
+    let key = match storage.content_addressed_images {
+        true => {
+            format!("images/{}/{digest}.{}", &digest[..2], format.extension())
+        },
+        false => {
+            let key = format!(
+                "{}/{}/{}-{}.{}",
+                event.year,
+                key_segment(&event.name),
+                doc_id,
+                page,
+                format.extension()
+            );
+            match revision {
+                Some(revision) => revised_key(&key, revision),
+                None => key,
+            }
+        },
+    };
+    debug!(doc_id, page, "Uploading page");
+    let upload = storage::open(client, storage, event.series)
+        .put(&key, &buf, format.content_type())
+        .await;
+    let url = match upload {
+        Ok(url) => url,
+        Err(why) => {
+            error!(doc_id, page, "Error uploading page: {why}");
+            Metrics::inc(&METRICS.upload_failures);
+            return None;
+        },
+    };
+    return Some(PageImage {
+        page: page as i32,
+        url,
+        hash: digest,
+        phash,
+    });
+}
+
+/// Uploads a page image and records it right away, for repairs where only
+/// a few pages are missing.
+async fn store_page(
+    client: &reqwest::Client,
+    storage: &StorageConfig,
+    path: &Path,
+    page: usize,
+    event: &Event,
+    doc_id: i64,
+    format: ImageFormat,
+    pool: &Pool<Postgres>,
+) -> bool {
+    let Some(image) = upload_page(
+        client, storage, path, page, event, doc_id, format, None, pool,
+    )
+    .await
+    else {
+        return false;
+    };
+    if let Err(why) = insert_image(doc_id, &image, pool).await {
+        error!(doc_id, page, "Error inserting image: {why}");
+        return false;
+    }
+    return true;
+}
+
+async fn mark_doc_done(
+    doc_id: i64,
+    pool: &Pool<Postgres>,
+) -> Result<i64, RunnerError> {
+    struct Id {
+        id: i64,
+    }
+    let id = sqlx::query_as!(
+        Id,
+        "UPDATE documents SET done = 1 WHERE id = $1 RETURNING id",
+        doc_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(id.id)
+}
+
+async fn set_page_count(
+    doc_id: i64,
+    page_count: i32,
+    pool: &Pool<Postgres>,
+) -> Result<(), RunnerError> {
+    // only touches the row when a conversion came up with a new count.
+    sqlx::query!(
+        "UPDATE documents SET page_count = $2 WHERE id = $1 AND page_count IS DISTINCT FROM $2",
+        doc_id,
+        page_count
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// A document as far as re-rendering its pages is concerned.
+struct StoredDoc {
+    id: i64,
+    title: String,
+    url: String,
+    mirror: String,
+    hash: Option<String>,
+}
+
+struct IncompleteDoc {
+    id: i64,
+    title: String,
+    url: String,
+    mirror: String,
+    hash: Option<String>,
+    event_id: i64,
+    event_name: String,
+    event_year: i32,
+    event_created: DateTime<Utc>,
+}
+
+async fn incomplete_documents(
+    series: Series,
+    pool: &Pool<Postgres>,
+) -> Result<Vec<(StoredDoc, Event)>, RunnerError> {
+    let series_str: String = series.into();
+    let docs = sqlx::query_as_unchecked!(
+        IncompleteDoc,
+        r#"SELECT
+        d.id,
+        d.title,
+        d.url,
+        d.mirror,
+        d.hash,
+        e.id AS event_id,
+        e.name AS event_name,
+        e.year AS event_year,
+        e.created AS event_created
+        FROM documents d
+        JOIN events e ON e.id = d.event
+        WHERE d.series = $1 AND d.page_count IS NOT NULL
+        AND d.page_count > (SELECT COUNT(*) FROM images i WHERE i.document = d.id)"#,
+        series_str
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let docs = docs
+        .into_iter()
+        .map(|doc| {
+            let event = Event {
+                id: Some(doc.event_id),
+                series,
+                year: doc.event_year,
+                name: doc.event_name,
+                created: doc.event_created,
+            };
+            let doc = StoredDoc {
+                id: doc.id,
+                title: doc.title,
+                url: doc.url,
+                mirror: doc.mirror,
+                hash: doc.hash,
+            };
+            (doc, event)
+        })
+        .collect();
+    Ok(docs)
+}
+
+async fn get_event(
+    event_id: i64,
+    pool: &Pool<Postgres>,
+) -> Result<Event, RunnerError> {
+    let event = sqlx::query_as_unchecked!(
+        Event,
+        r#"SELECT id as "id?", name, year, created, series as "series: Series" FROM events WHERE id = $1"#,
+        event_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(event)
+}
+
+async fn event_documents(
+    event_id: i64,
+    pool: &Pool<Postgres>,
+) -> Result<Vec<StoredDoc>, RunnerError> {
+    let docs = sqlx::query_as_unchecked!(
+        StoredDoc,
+        "SELECT id, title, url, mirror, hash FROM documents WHERE event = $1 ORDER BY id",
+        event_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(docs)
+}
+
+async fn delete_images_from<'c>(
+    doc_id: i64,
+    page: i32,
+    executor: impl PgExecutor<'c>,
+) -> Result<(), RunnerError> {
+    sqlx::query!(
+        "DELETE FROM images WHERE document = $1 AND pagenum >= $2",
+        doc_id,
+        page
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+async fn image_pages(
+    doc_id: i64,
+    pool: &Pool<Postgres>,
+) -> Result<Vec<i32>, RunnerError> {
+    let pages = sqlx::query_scalar!(
+        "SELECT pagenum FROM images WHERE document = $1",
+        doc_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(pages)
+}
+
+async fn mark_removed_documents(
+    event_id: i64,
+    urls: &[String],
+    pool: &Pool<Postgres>,
+) -> Result<u64, RunnerError> {
+    let result = sqlx::query!(
+        "UPDATE documents SET removed = NOW() WHERE event = $1 AND removed IS NULL AND url <> ALL($2)",
+        event_id,
+        urls
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Flips `notified` if nobody did yet, only the caller that flipped it
+/// sends the notification.
+async fn claim_notification(
+    doc_id: i64,
+    pool: &Pool<Postgres>,
+) -> Result<bool, RunnerError> {
+    let claimed = sqlx::query_scalar!(
+        "UPDATE documents SET notified = true WHERE id = $1 AND notified = false RETURNING id",
+        doc_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(claimed.is_some())
+}
+
+/// Hands back a claim after the notification couldn't be sent.
+async fn release_notification(
+    doc_id: i64,
+    pool: &Pool<Postgres>,
+) -> Result<(), RunnerError> {
+    sqlx::query!("UPDATE documents SET notified = false WHERE id = $1", doc_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+async fn mark_doc_notified(
+    doc_id: i64,
+    pool: &Pool<Postgres>,
+) -> Result<(), RunnerError> {
+    sqlx::query!("UPDATE documents SET notified = true WHERE id = $1", doc_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Remembers when `series` last finished a scan, so a single broken series
+/// stands out.
+async fn record_scan_state(
+    series: Series,
+    document_count: i32,
+    validators: &Validators,
+    pool: &Pool<Postgres>,
+) -> Result<(), RunnerError> {
+    let series: String = series.into();
+    sqlx::query!(
+        "INSERT INTO scan_state (series, last_scanned, last_document_count, etag, last_modified) VALUES ($1, now(), $2, $3, $4) ON CONFLICT (series) DO UPDATE SET last_scanned = EXCLUDED.last_scanned, last_document_count = EXCLUDED.last_document_count, etag = EXCLUDED.etag, last_modified = EXCLUDED.last_modified",
+        series,
+        document_count,
+        validators.etag,
+        validators.last_modified
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+struct ScanState {
+    /// documents seen by the last completed scan.
+    pub last_document_count: i32,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+async fn scan_state(
+    series: Series,
+    pool: &Pool<Postgres>,
+) -> Result<Option<ScanState>, RunnerError> {
+    let series: String = series.into();
+    let state = sqlx::query_as_unchecked!(
+        ScanState,
+        "SELECT last_document_count, etag, last_modified FROM scan_state WHERE series = $1",
+        series
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(state)
+}
+
+async fn insert_image(
+    doc_id: i64,
+    image: &PageImage,
+    pool: &Pool<Postgres>,
+) -> Result<(), RunnerError> {
+    sqlx::query!(
+        "INSERT INTO images (document, url, pagenum, hash, phash) VALUES ($1, $2, $3, $4, $5) ON CONFLICT (document, pagenum) DO UPDATE SET url = EXCLUDED.url, hash = EXCLUDED.hash, phash = EXCLUDED.phash",
+        doc_id,
+        image.url,
+        image.page,
+        image.hash,
+        image.phash
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Records all pages of a document with a single statement.
+async fn insert_images<'c>(
+    doc_id: i64,
+    images: &[PageImage],
+    executor: impl PgExecutor<'c>,
+) -> Result<(), RunnerError> {
+    if images.is_empty() {
+        return Ok(());
+    }
+    let urls: Vec<String> = images.iter().map(|i| i.url.clone()).collect();
+    let pages: Vec<i32> = images.iter().map(|i| i.page).collect();
+    let hashes: Vec<String> = images.iter().map(|i| i.hash.clone()).collect();
+    let phashes: Vec<Option<String>> =
+        images.iter().map(|i| i.phash.clone()).collect();
+    sqlx::query!(
+        "INSERT INTO images (document, url, pagenum, hash, phash) SELECT $1, * FROM UNNEST($2::text[], $3::int[], $4::text[], $5::text[]) ON CONFLICT (document, pagenum) DO UPDATE SET url = EXCLUDED.url, hash = EXCLUDED.hash, phash = EXCLUDED.phash",
+        doc_id,
+        &urls,
+        &pages,
+        &hashes,
+        &phashes as &[Option<String>]
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Url of an already uploaded page with the same content.
+async fn image_url_for_hash(
+    hash: &str,
+    pool: &Pool<Postgres>,
+) -> Result<Option<String>, RunnerError> {
+    let url = sqlx::query_scalar!(
+        "SELECT url FROM images WHERE hash = $1 LIMIT 1",
+        hash
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(url)
+}
+
+/// `key` with `revision` before its extension, so a re-upload doesn't
+/// overwrite the object in use.
+fn revised_key(
+    key: &str,
+    revision: &str,
+) -> String {
+    return match key.rsplit_once('.') {
+        Some((stem, extension)) => format!("{stem}-{revision}.{extension}"),
+        None => format!("{key}-{revision}"),
+    };
+}
+
+/// Key of a document's mirror, the shortened content hash keeps revisions
+/// sharing a title apart.
+fn mirror_key(
+    title: &str,
+    event: &str,
+    year: i16,
+    hash: &str,
+    media_type: MediaType,
+) -> String {
+    let title = key_segment(title);
+    let event = key_segment(event);
+    return format!(
+        "mirror/{year}/{event}/{title}-{}.{}",
+        &hash[..16],
+        media_type.extension()
+    );
+}
+
+/// Mirrors a document to `mirror/{year}/{event}/{title}-{hash}.{ext}`.
+async fn upload_mirror(
+    client: &reqwest::Client,
+    storage: &StorageConfig,
+    series: Series,
+    title: &str,
+    event: &str,
+    year: i16,
+    download: &DownloadedFile,
+) -> Result<String, RunnerError> {
+    let content_type = download.media_type.content_type();
+    let key =
+        mirror_key(title, event, year, &download.hash, download.media_type);
+    return storage::open(client, storage, series)
+        .put(&key, &download.body, content_type)
+        .await;
+}
+
+/// Whether `series` already has a document with this content, other series
+/// posting the same file still get their own document.
+async fn document_hash_exists(
+    hash: &str,
+    series: Series,
+    pool: &Pool<Postgres>,
+) -> Result<bool, RunnerError> {
+    let series: String = series.into();
+    let exists = sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM documents WHERE hash = $1 AND series = $2) AS "exists!""#,
+        hash,
+        series
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(exists)
+}
+
+async fn mirror_referenced(
+    mirror: &str,
+    pool: &Pool<Postgres>,
+) -> Result<bool, RunnerError> {
+    let exists = sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM documents WHERE mirror = $1) AS "exists!""#,
+        mirror
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(exists)
+}
+
+async fn image_referenced(
+    url: &str,
+    pool: &Pool<Postgres>,
+) -> Result<bool, RunnerError> {
+    let exists = sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM images WHERE url = $1) AS "exists!""#,
+        url
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(exists)
+}
+
+struct DownloadedFile {
+    pub path: PathBuf,
+    pub body: Vec<u8>,
+    pub media_type: MediaType,
+    /// from the response's Content-Disposition.
+    pub original_filename: Option<String>,
+    /// sha256 of the body, used to detect re-published documents.
+    pub hash: String,
+}
+
+/// Downloads a document from the FIA, resuming with a range request when
+/// the connection drops halfway and the server supports it.
+async fn download_file(
+    fia: &FiaClient,
+    tmp_dir: &Path,
+    url: &str,
+    name: &str,
+    max_bytes: u64,
+) -> Result<DownloadedFile, RunnerError> {
+    let mut body = Vec::new();
+    let mut content_type = String::new();
+    let mut filename = None;
+    // what the received part was, so a resume can't stitch two files.
+    let mut validator: Option<String> = None;
+    let mut attempt = 0;
+    loop {
+        let response = match &validator {
+            Some(validator) if !body.is_empty() => {
+                fia.get_from(url, body.len() as u64, validator).await
+            },
+            _ => fia.get(url).await,
+        };
+        let mut resumable = false;
+        let result = match response {
+            Ok(response) if !response.status().is_success() => {
+                Err(RunnerError::status(&response))
+            },
+            Ok(response) => {
+                // anything but a 206 is the whole document again.
+                if response.status() != StatusCode::PARTIAL_CONTENT {
+                    body.clear();
+                }
+                let header = |name: HeaderName| {
+                    response
+                        .headers()
+                        .get(name)
+                        .and_then(|value| value.to_str().ok())
+                        .map(|value| value.to_owned())
+                };
+                // weak etags can't validate a range, last modified can.
+                validator = header(ETAG)
+                    .filter(|etag| !etag.starts_with("W/"))
+                    .or_else(|| header(LAST_MODIFIED));
+                resumable = validator.is_some()
+                    && (response.status() == StatusCode::PARTIAL_CONTENT
+                        || header(ACCEPT_RANGES).is_some_and(|v| v == "bytes"));
+                content_type = response_content_type(&response);
+                filename = response_filename(&response).or(filename);
+                read_body(response, &mut body, max_bytes).await
+            },
+            Err(why) => Err(RunnerError::Download(why)),
+        };
+        match result {
+            Ok(()) => break,
+            Err(RunnerError::Download(why)) if attempt < DOWNLOAD_RETRIES => {
+                if !resumable {
+                    body.clear();
+                }
+                let backoff = Duration::from_secs(1 << attempt);
+                warn!(
+                    url,
+                    received = body.len(),
+                    retry_in = backoff.as_secs(),
+                    "Download interrupted, retrying: {why}"
+                );
+                tokio::time::sleep(backoff).await;
+                attempt += 1;
+            },
+            Err(why) => return Err(why),
+        }
+    }
+    return save_download(body, &content_type, filename, tmp_dir, name).await;
+}
+
+/// Downloads a document from our own mirror, which unlike fia.com doesn't
+/// pull documents, so re-conversions prefer it.
+async fn download_from_mirror(
+    client: &reqwest::Client,
+    tmp_dir: &Path,
+    url: &str,
+    name: &str,
+    max_bytes: u64,
+) -> Result<DownloadedFile, RunnerError> {
+    // the local backend's urls point straight into its directory.
+    if let Some(path) = url.strip_prefix("file://") {
+        let body = tokio::fs::read(path).await?;
+        if body.len() as u64 > max_bytes {
+            return Err(RunnerError::Content(format!(
+                "document is larger than {max_bytes} bytes"
+            )));
+        }
+        return save_download(body, "unknown", None, tmp_dir, name).await;
+    }
+    let response =
+        client.get(url).send().await.map_err(RunnerError::Download)?;
+    if !response.status().is_success() {
+        return Err(RunnerError::status(&response));
+    }
+    let content_type = response_content_type(&response);
+    let filename = response_filename(&response);
+    let mut body = Vec::new();
+    read_body(response, &mut body, max_bytes).await?;
+    return save_download(body, &content_type, filename, tmp_dir, name).await;
+}
+
+fn response_content_type(response: &reqwest::Response) -> String {
+    return response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("unknown")
+        .to_owned();
+}
+
+/// The file name of a `Content-Disposition` header, the RFC 5987 encoded
+/// `filename*` wins over a plain `filename`.
+fn response_filename(response: &reqwest::Response) -> Option<String> {
+    let header = response.headers().get(CONTENT_DISPOSITION)?;
+    let header = String::from_utf8_lossy(header.as_bytes());
+    let param = |name: &str| {
+        header.split(';').find_map(|part| {
+            let (key, value) = part.split_once('=')?;
+            (key.trim().eq_ignore_ascii_case(name))
+                .then(|| value.trim().trim_matches('"').to_owned())
+        })
+    };
+    let filename = param("filename*")
+        .and_then(|value| {
+            // `UTF-8''Doc%2034.pdf`, the charset is always utf-8 in practice.
+            let (_, encoded) = value.split_once("''")?;
+            urlencoding::decode(encoded).ok().map(|name| name.into_owned())
+        })
+        .or_else(|| param("filename"))?;
+    return Some(filename).filter(|name| !name.is_empty());
+}
+
+/// Streams a response onto the end of `body`, keeping what was received
+/// when the connection drops.
+async fn read_body(
+    mut response: reqwest::Response,
+    body: &mut Vec<u8>,
+    max_bytes: u64,
+) -> Result<(), RunnerError> {
+    let too_large = || {
+        RunnerError::Content(format!(
+            "document is larger than {max_bytes} bytes"
+        ))
+    };
+    if response
+        .content_length()
+        .is_some_and(|len| body.len() as u64 + len > max_bytes)
+    {
+        return Err(too_large());
+    }
+    // the content length can be missing or lie, so count while streaming.
+    while let Some(chunk) =
+        response.chunk().await.map_err(RunnerError::Download)?
+    {
+        if (body.len() + chunk.len()) as u64 > max_bytes {
+            return Err(too_large());
+        }
+        body.extend_from_slice(&chunk);
+    }
+    return Ok(());
+}
+
+/// Writes a downloaded document to `{tmp_dir}/{name}.{ext}`.
+async fn save_download(
+    body: Vec<u8>,
+    content_type: &str,
+    original_filename: Option<String>,
+    tmp_dir: &Path,
+    name: &str,
+) -> Result<DownloadedFile, RunnerError> {
+    // error pages and login walls come back as html, don't mirror those.
+    let Some(media_type) = MediaType::sniff(&body) else {
+        return Err(RunnerError::Content(format!(
+            "not a pdf or image, got content-type {content_type}"
+        )));
+    };
+    let path = tmp_dir.join(format!("{name}.{}", media_type.extension()));
+    tokio::fs::write(&path, &body).await?;
+    // ensure we're actually pointing to a legit file.
+    path.try_exists()?;
+    Ok(DownloadedFile {
+        path,
+        hash: sha256::digest(body.as_slice()),
+        body,
+        media_type,
+        original_filename,
+    })
+}
+
+async fn insert_event(
+    pool: &Pool<Postgres>,
+    year: i16,
+    event: &ParserEvent,
+    series: Series,
+) -> Result<Event, RunnerError> {
+    struct Inserted {
+        id: i64,
+        created: DateTime<Utc>,
+    }
+
+    let mut db_event = Event {
+        id: None,
+        series,
+        year: year as i32,
+        name: event.title.as_ref().unwrap().clone(),
+        created: Utc::now(),
+    };
+    let series: String = db_event.series.into();
+    // another runner may have created the event since we looked, the no-op
+    // update makes the losing insert return the existing row.
+    let res: Inserted = sqlx::query_as_unchecked!(Inserted, "INSERT INTO events (series, year, name, created, current, new) VALUES ($1, $2, $3, $4, 0, 1) ON CONFLICT (name, year, series) DO UPDATE SET name = EXCLUDED.name RETURNING id, created",
+    series,
+    db_event.year,
+    db_event.name,
+    db_event.created).fetch_one(pool).await?;
+    db_event.id = Some(res.id);
+    db_event.created = res.created;
+    Ok(db_event)
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::postgres::PgPoolOptions;
+    use wiremock::{
+        matchers::{method, path, path_regex},
+        Mock, MockServer, Request, ResponseTemplate,
+    };
+
+    use super::*;
+
+    /// A one page pdf showing `text`, documents need distinct content or
+    /// the hash check skips them as re-published.
+    fn pdf(text: &str) -> Vec<u8> {
+        let text = text.replace(['(', ')', '\\'], "");
+        let content = format!("BT /F1 12 Tf 72 720 Td ({text}) Tj ET");
+        let objects = [
+            "<< /Type /Catalog /Pages 2 0 R >>".to_owned(),
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_owned(),
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] /Contents 4 0 R /Resources << /Font << /F1 5 0 R >> >> >>".to_owned(),
+            format!(
+                "<< /Length {} >>\nstream\n{content}\nendstream",
+                content.len()
+            ),
+            "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>".to_owned(),
+        ];
+        let mut pdf = String::from("%PDF-1.4\n");
+        let mut offsets = vec![];
+        for (index, object) in objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            pdf.push_str(&format!("{} 0 obj\n{object}\nendobj\n", index + 1));
+        }
+        let xref = pdf.len();
+        let size = objects.len() + 1;
+        pdf.push_str(&format!("xref\n0 {size}\n0000000000 65535 f \n"));
+        for offset in offsets {
+            pdf.push_str(&format!("{offset:010} 00000 n \n"));
+        }
+        pdf.push_str(&format!(
+            "trailer\n<< /Size {size} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n"
+        ));
+        return pdf.into_bytes();
+    }
+
+    // the database and environment are shared, scans run one at a time.
+    static DATABASE: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+    /// Serves `pages` as the 2024 documents of their series, every pdf gets
+    /// content of its own.
+    async fn mock_fia(pages: &[(Series, &'static [u8])]) -> MockServer {
+        let fia_mock = MockServer::start().await;
+        for (series, html) in pages {
+            Mock::given(method("GET"))
+                .and(path(series.docs_path(2024, 2043)))
+                .respond_with(
+                    ResponseTemplate::new(200)
+                        .set_body_raw(html.to_vec(), "text/html"),
+                )
+                .mount(&fia_mock)
+                .await;
+        }
+        Mock::given(method("GET"))
+            .and(path_regex(r"\.pdf$"))
+            .respond_with(|request: &Request| {
+                ResponseTemplate::new(200)
+                    .set_body_raw(pdf(request.url.path()), "application/pdf")
+            })
+            .mount(&fia_mock)
+            .await;
+        return fia_mock;
+    }
+
+    /// A config against `fia_mock` storing into a fresh directory, and an
+    /// emptied database from `TEST_DATABASE_URL`.
+    async fn test_env(fia_mock: &MockServer) -> (Config, Pool<Postgres>) {
+        let database_url = std::env::var("TEST_DATABASE_URL")
+            .expect("TEST_DATABASE_URL is not set");
+        let storage_dir = std::env::temp_dir()
+            .join(format!("fia-docs-api-e2e-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&storage_dir);
+        std::env::set_var("DATABASE_URL", &database_url);
+        std::env::set_var("FIA_BASE_URL", fia_mock.uri());
+        std::env::set_var("SEASON_YEAR", "2024");
+        std::env::set_var("FIA_SEASON_ID", "2043");
+        std::env::set_var("SERIES", "f1,f2");
+        std::env::set_var("STORAGE_BACKEND", "local");
+        std::env::set_var("LOCAL_STORAGE_DIR", &storage_dir);
+        std::env::set_var("TMP_DIR", storage_dir.join("tmp"));
+        std::env::remove_var("DISCORD_WEBHOOK_URL");
+        let config = Config::from_env().unwrap();
+        assert!(config.magick, "imagemagick isn't installed");
+        create_tmp_dir(&config.tmp_dir).unwrap();
+
+        let pool = PgPoolOptions::new().connect(&database_url).await.unwrap();
+        sqlx::migrate!().run(&pool).await.unwrap();
+        sqlx::query("TRUNCATE events, documents, images, scan_state")
+            .execute(&pool)
+            .await
+            .unwrap();
+        return (config, pool);
+    }
+
+    async fn scan(
+        pool: &Pool<Postgres>,
+        config: &Config,
+        series: Series,
+    ) -> ScanStats {
+        let client = reqwest::Client::new();
+        let fia = FiaClient::new(reqwest::Client::new(), Duration::ZERO);
+        let stop = AtomicBool::new(false);
+        let mut stats = ScanStats::default();
+        let completed = f1_runner(
+            pool,
+            &client,
+            &fia,
+            config,
+            &stop,
+            series,
+            &mut LocalCache::default(),
+            &mut stats,
+        )
+        .await;
+        assert!(completed);
+        assert_eq!(stats.errors, 0);
+        return stats;
+    }
+
+    /// Scans a canned F1 season from a mock fia.com into local storage.
+    /// Needs imagemagick and a throwaway Postgres in `TEST_DATABASE_URL`,
+    /// its tables get emptied.
+    #[tokio::test]
+    #[ignore = "needs imagemagick and TEST_DATABASE_URL"]
+    async fn mirrors_a_season_end_to_end() {
+        let _database = DATABASE.lock().await;
+        let fia_mock = mock_fia(&[(
+            Series::f1,
+            include_bytes!("../../tests/fixtures/f1_2024.html"),
+        )])
+        .await;
+        let (config, pool) = test_env(&fia_mock).await;
+        let stats = scan(&pool, &config, Series::f1).await;
+        assert_eq!(stats.documents_mirrored, 5);
+
+        let events: Vec<String> =
+            sqlx::query_scalar("SELECT name FROM events ORDER BY name")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(events, ["Bahrain Grand Prix", "Pre-Season Testing"]);
+        let docs: Vec<(String, String, Option<i32>)> = sqlx::query_as(
+            "SELECT url, mirror, page_count FROM documents ORDER BY id",
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(docs.len(), 5);
+        for (url, mirror, page_count) in &docs {
+            assert!(url.starts_with(&fia_mock.uri()), "{url}");
+            assert_eq!(*page_count, Some(1), "{url}");
+            let file = mirror.strip_prefix("file://").unwrap();
+            assert!(Path::new(file).is_file(), "{mirror}");
+        }
+        let images: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM images")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(images, 5);
+
+        pool.close().await;
+    }
+
+    /// F1 and F2 both race a "Bahrain Grand Prix" in 2024, one of their
+    /// documents even has the same content.
+    #[tokio::test]
+    #[ignore = "needs imagemagick and TEST_DATABASE_URL"]
+    async fn keeps_series_with_the_same_event_apart() {
+        let _database = DATABASE.lock().await;
+        let fia_mock = mock_fia(&[
+            (Series::f1, include_bytes!("../../tests/fixtures/f1_2024.html")),
+            (Series::f2, include_bytes!("../../tests/fixtures/f2_2024.html")),
+        ])
+        .await;
+        for shared in [
+            "/sites/default/files/decision-document/2024%20Bahrain%20Grand%20Prix%20-%20Final%20Race%20Classification.pdf",
+            "/sites/default/files/decision-document/2024%20Bahrain%20Grand%20Prix%20-%20F2%20Sprint%20Race%20Starting%20Grid.pdf",
+        ] {
+            Mock::given(method("GET"))
+                .and(path(shared))
+                .respond_with(
+                    ResponseTemplate::new(200)
+                        .set_body_raw(pdf("shared"), "application/pdf"),
+                )
+                .with_priority(1)
+                .mount(&fia_mock)
+                .await;
+        }
+        let (config, pool) = test_env(&fia_mock).await;
+        assert_eq!(
+            scan(&pool, &config, Series::f1).await.documents_mirrored,
+            5
+        );
+        assert_eq!(
+            scan(&pool, &config, Series::f2).await.documents_mirrored,
+            3
+        );
+
+        let events: Vec<(String, i64)> = sqlx::query_as(
+            r#"
+        SELECT e.series, COUNT(d.id)
+        FROM events e
+        JOIN documents d ON d.event = e.id
+        WHERE e.name = 'Bahrain Grand Prix' AND e.year = 2024
+        GROUP BY e.id, e.series
+        ORDER BY e.series"#,
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(events, [("f1".to_owned(), 3), ("f2".to_owned(), 1)]);
+        let crossed: i64 = sqlx::query_scalar(
+            r#"
+        SELECT COUNT(*)
+        FROM documents d
+        JOIN events e ON e.id = d.event
+        WHERE d.series <> e.series"#,
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(crossed, 0);
+        // the identical pdf is still a document of each series.
+        let shared: Vec<String> = sqlx::query_scalar(
+            r#"
+        SELECT series
+        FROM documents
+        WHERE hash = (SELECT hash FROM documents WHERE title LIKE '%Starting Grid%')
+        ORDER BY series"#,
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(shared, ["f1", "f2"]);
+
+        pool.close().await;
+    }
+
+    /// A notification the webhook rejected goes out on the next scan.
+    #[tokio::test]
+    #[ignore = "needs imagemagick and TEST_DATABASE_URL"]
+    async fn retries_failed_notifications() {
+        let _database = DATABASE.lock().await;
+        let fia_mock = mock_fia(&[(
+            Series::f1,
+            include_bytes!("../../tests/fixtures/f1_2024.html"),
+        )])
+        .await;
+        Mock::given(method("POST"))
+            .and(path("/webhook"))
+            .respond_with(ResponseTemplate::new(500))
+            .up_to_n_times(5)
+            .with_priority(1)
+            .mount(&fia_mock)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/webhook"))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(5)
+            .mount(&fia_mock)
+            .await;
+        let (config, pool) = test_env(&fia_mock).await;
+        std::env::set_var(
+            "DISCORD_WEBHOOK_URL",
+            format!("{}/webhook", fia_mock.uri()),
+        );
+        let unsent = "SELECT COUNT(*) FROM documents WHERE notified = false";
+
+        scan(&pool, &config, Series::f1).await;
+        let count: i64 =
+            sqlx::query_scalar(unsent).fetch_one(&pool).await.unwrap();
+        assert_eq!(count, 5);
+        scan(&pool, &config, Series::f1).await;
+        let count: i64 =
+            sqlx::query_scalar(unsent).fetch_one(&pool).await.unwrap();
+        assert_eq!(count, 0);
+
+        std::env::remove_var("DISCORD_WEBHOOK_URL");
+        pool.close().await;
+    }
+
+    #[tokio::test]
+    async fn reads_local_mirrors_from_disk() {
+        let dir = std::env::temp_dir()
+            .join(format!("fia-docs-api-mirror-{}", std::process::id()));
+        create_tmp_dir(&dir).unwrap();
+        let mirror = dir.join("Doc%201%20-%20Event%20Notes.pdf");
+        let body = pdf("Doc 1 - Event Notes");
+        std::fs::write(&mirror, &body).unwrap();
+        let url = format!("file://{}", mirror.display());
+
+        let client = reqwest::Client::new();
+        let download =
+            download_from_mirror(&client, &dir, &url, "reconvert", 1 << 20)
+                .await
+                .unwrap();
+        assert_eq!(download.media_type, MediaType::Pdf);
+        assert_eq!(download.hash, sha256::digest(body.as_slice()));
+        assert_eq!(download.path, dir.join("reconvert.pdf"));
+        assert!(download_from_mirror(&client, &dir, &url, "reconvert", 16)
+            .await
+            .is_err());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    /// An event publishing on both sides of midnight is still one event.
+    #[tokio::test]
+    #[ignore = "needs imagemagick and TEST_DATABASE_URL"]
+    async fn keeps_documents_across_midnight_in_one_event() {
+        let _database = DATABASE.lock().await;
+        let fia_mock = mock_fia(&[(
+            Series::f1,
+            include_bytes!("../../tests/fixtures/cross_midnight.html"),
+        )])
+        .await;
+        let (config, pool) = test_env(&fia_mock).await;
+        assert_eq!(
+            scan(&pool, &config, Series::f1).await.documents_mirrored,
+            5
+        );
+
+        let events: Vec<(String, i64, i64)> = sqlx::query_as(
+            r#"
+        SELECT e.name, COUNT(d.id), COUNT(DISTINCT (d.published AT TIME ZONE 'UTC')::date)
+        FROM events e
+        JOIN documents d ON d.event = e.id
+        GROUP BY e.id, e.name
+        ORDER BY e.name"#,
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(
+            events,
+            [
+                ("Las Vegas Grand Prix".to_owned(), 4, 2),
+                ("Sao Paulo Grand Prix".to_owned(), 1, 1)
+            ]
+        );
+
+        pool.close().await;
+    }
+
+    #[test]
+    fn revised_key_keeps_the_extension() {
+        assert_eq!(
+            revised_key(
+                "2024/Bahrain%20Grand%20Prix/12-0.jpg",
+                "20240302192700"
+            ),
+            "2024/Bahrain%20Grand%20Prix/12-0-20240302192700.jpg"
+        );
+        assert_eq!(revised_key("check", "1"), "check-1");
+    }
+
+    #[test]
+    fn mirror_key_encodes_every_segment() {
+        let hash = "ab".repeat(32);
+        assert_eq!(
+            mirror_key(
+                "Doc 5 - Décision",
+                "São Paulo Grand Prix",
+                2024,
+                &hash,
+                MediaType::Pdf
+            ),
+            "mirror/2024/S%C3%A3o%20Paulo%20Grand%20Prix/Doc%205%20-%20D%C3%A9cision-abababababababab.pdf"
+        );
+    }
 }
@@ -0,0 +1,150 @@
+use std::num::NonZeroI16;
+
+use reqwest::header::{HeaderMap, HeaderValue, ACCEPT};
+use serde::Deserialize;
+
+use super::{
+    fia::FiaClient,
+    parser::{normalize_whitespace, ParserDocument, ParserEvent, Season},
+};
+use crate::error::RunnerError;
+
+/// A channel's notice board, its events with their documents.
+#[derive(Deserialize)]
+struct Channel {
+    #[serde(default)]
+    events: Vec<ChannelEvent>,
+}
+
+#[derive(Deserialize)]
+struct ChannelEvent {
+    name: String,
+    #[serde(default)]
+    documents: Vec<ChannelDocument>,
+}
+
+#[derive(Deserialize)]
+struct ChannelDocument {
+    title: Option<String>,
+    url: Option<String>,
+    /// an RFC 3339 timestamp, `parse_date` takes it as is.
+    published: Option<String>,
+}
+
+/// Fetches a season from a Sportity channel, used by series that don't
+/// publish their documents on fia.com. Channels are a web app, so this asks
+/// for the json it loads instead of markup `HTMLParser` could read.
+pub async fn get_season(
+    fia: &FiaClient,
+    url: &str,
+    year: NonZeroI16,
+) -> Result<Season, RunnerError> {
+    let mut headers = HeaderMap::new();
+    headers.insert(ACCEPT, HeaderValue::from_static("application/json"));
+    let response =
+        fia.get_with(url, headers).await.map_err(RunnerError::Download)?;
+    if !response.status().is_success() {
+        return Err(RunnerError::status(&response));
+    }
+    let body = response.bytes().await.map_err(RunnerError::Download)?;
+    return parse_channel(&body, year, url);
+}
+
+/// Turns a channel into a season, relative document links are resolved
+/// against `url`.
+pub fn parse_channel(
+    json: &[u8],
+    year: NonZeroI16,
+    url: &str,
+) -> Result<Season, RunnerError> {
+    let base = reqwest::Url::parse(url)
+        .map_err(|why| RunnerError::Parse(format!("bad url {url}: {why}")))?;
+    let channel: Channel = serde_json::from_slice(json).map_err(|why| {
+        RunnerError::Parse(format!("sportity channel {url}: {why}"))
+    })?;
+    let mut events = vec![];
+    for event in channel.events {
+        let title = normalize_whitespace(&event.name);
+        if title.is_empty() {
+            continue;
+        }
+        let mut parsed = ParserEvent {
+            title: Some(title),
+            season: Some(year),
+            documents: vec![],
+            incomplete: 0,
+        };
+        for doc in event.documents {
+            let title = doc.title.as_deref().map(normalize_whitespace);
+            let url = doc.url.and_then(|url| base.join(url.trim()).ok());
+            match (title, url, doc.published) {
+                (Some(title), Some(url), Some(date)) if !title.is_empty() => {
+                    parsed.documents.push(ParserDocument {
+                        title,
+                        url: url.to_string(),
+                        date: date.trim().to_owned(),
+                    })
+                },
+                // kept count of so the reconcile pass can tell it's missing.
+                _ => parsed.incomplete += 1,
+            }
+        }
+        events.push(parsed);
+    }
+    return Ok(Season {
+        year,
+        events,
+        next_page: None,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::parser::parse_date;
+
+    #[test]
+    fn parses_a_channel_into_events() {
+        let json = include_bytes!("../../tests/fixtures/f1a_2025.json");
+        let season = parse_channel(
+            json,
+            NonZeroI16::new(2025).unwrap(),
+            "https://webapp.sportity.com/channel/F1A2025",
+        )
+        .unwrap();
+        let titles: Vec<&str> = season
+            .events
+            .iter()
+            .map(|event| event.title.as_deref().unwrap())
+            .collect();
+        assert_eq!(titles, ["Jeddah", "Shanghai"]);
+
+        let jeddah = &season.events[0];
+        assert_eq!(jeddah.documents.len(), 2);
+        assert_eq!(jeddah.incomplete, 1);
+        let doc = &jeddah.documents[0];
+        assert_eq!(doc.title, "Doc 14 - Race 2 Final Classification");
+        assert_eq!(
+            doc.url,
+            "https://webapp.sportity.com/files/F1A2025/Jeddah%20-%20Race%202%20Final%20Classification.pdf"
+        );
+        assert_eq!(
+            parse_date(&doc.date).unwrap().to_rfc3339(),
+            "2025-04-20T15:42:00+00:00"
+        );
+        // absolute links stay as they are.
+        assert_eq!(
+            jeddah.documents[1].url,
+            "https://cdn.sportity.com/F1A2025/Jeddah%20-%20Decision%20-%20Car%2028.pdf"
+        );
+        assert_eq!(season.events[1].documents.len(), 1);
+    }
+
+    #[test]
+    fn rejects_anything_but_a_channel() {
+        let year = NonZeroI16::new(2025).unwrap();
+        let url = "https://webapp.sportity.com/channel/F1A2025";
+        assert!(parse_channel(b"<!DOCTYPE html>", year, url).is_err());
+        assert!(parse_channel(b"{}", year, url).unwrap().events.is_empty());
+    }
+}
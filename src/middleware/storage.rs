@@ -0,0 +1,204 @@
+use std::path::Path;
+
+use futures::future::BoxFuture;
+use reqwest::header::ETAG;
+use tracing::{debug, warn};
+
+use super::s3::{
+    signed_delete, signed_put, upload_multipart, MULTIPART_THRESHOLD,
+};
+use crate::{
+    config::{S3Config, StorageBackend, StorageConfig},
+    error::RunnerError,
+    model::series::Series,
+};
+
+/// A place mirrors and page images are stored, keys are relative to the
+/// configured prefix.
+pub trait Storage {
+    /// Stores `body` under `key`, returns the url it's served from.
+    fn put<'a>(
+        &'a self,
+        key: &'a str,
+        body: &'a [u8],
+        content_type: &'a str,
+    ) -> BoxFuture<'a, Result<String, RunnerError>>;
+
+    /// Removes `key`, missing keys aren't an error.
+    fn delete<'a>(
+        &'a self,
+        key: &'a str,
+    ) -> BoxFuture<'a, Result<(), RunnerError>>;
+}
+
+/// A bucket, objects get the configured acl.
+pub struct S3Storage<'a> {
+    client: &'a reqwest::Client,
+    s3: &'a S3Config,
+    prefix: &'a str,
+    public_url: String,
+}
+
+/// A directory, for local development without a bucket.
+pub struct LocalStorage<'a> {
+    dir: &'a Path,
+    prefix: &'a str,
+    public_url: String,
+}
+
+/// The configured backend, handing out the urls `series` is served from.
+pub fn open<'a>(
+    client: &'a reqwest::Client,
+    storage: &'a StorageConfig,
+    series: Series,
+) -> Box<dyn Storage + Send + Sync + 'a> {
+    let public_url = storage.public(series, "");
+    return match &storage.backend {
+        StorageBackend::S3(s3) => Box::new(S3Storage {
+            client,
+            s3,
+            prefix: &storage.prefix,
+            public_url,
+        }),
+        StorageBackend::Local(dir) => Box::new(LocalStorage {
+            dir,
+            prefix: &storage.prefix,
+            public_url,
+        }),
+    };
+}
+
+impl Storage for S3Storage<'_> {
+    fn put<'a>(
+        &'a self,
+        key: &'a str,
+        body: &'a [u8],
+        content_type: &'a str,
+    ) -> BoxFuture<'a, Result<String, RunnerError>> {
+        return Box::pin(async move {
+            let path = format!("{}{key}", self.prefix);
+            put_s3(self.client, self.s3, &path, body, content_type).await?;
+            return Ok(format!("{}{key}", self.public_url));
+        });
+    }
+
+    fn delete<'a>(
+        &'a self,
+        key: &'a str,
+    ) -> BoxFuture<'a, Result<(), RunnerError>> {
+        return Box::pin(async move {
+            let url = self.s3.endpoint(&format!("{}{key}", self.prefix));
+            return signed_delete(self.client, self.s3, &url).await;
+        });
+    }
+}
+
+impl Storage for LocalStorage<'_> {
+    fn put<'a>(
+        &'a self,
+        key: &'a str,
+        body: &'a [u8],
+        _content_type: &'a str,
+    ) -> BoxFuture<'a, Result<String, RunnerError>> {
+        return Box::pin(async move {
+            let path = self.dir.join(format!("{}{key}", self.prefix));
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            tokio::fs::write(&path, body).await?;
+            return Ok(format!("{}{key}", self.public_url));
+        });
+    }
+
+    fn delete<'a>(
+        &'a self,
+        key: &'a str,
+    ) -> BoxFuture<'a, Result<(), RunnerError>> {
+        return Box::pin(async move {
+            let path = self.dir.join(format!("{}{key}", self.prefix));
+            return match tokio::fs::remove_file(path).await {
+                Err(why) if why.kind() != std::io::ErrorKind::NotFound => {
+                    Err(why.into())
+                },
+                _ => Ok(()),
+            };
+        });
+    }
+}
+
+async fn put_s3(
+    client: &reqwest::Client,
+    s3: &S3Config,
+    key: &str,
+    body: &[u8],
+    content_type: &str,
+) -> Result<(), RunnerError> {
+    if body.len() > MULTIPART_THRESHOLD {
+        return upload_multipart(client, s3, key, body, content_type).await;
+    }
+    let url = s3.endpoint(key);
+    let response = signed_put(client, s3, &url, body, content_type).await?;
+
+    // the etag of a single part upload is the md5 of the body, a mismatch
+    // means the object got truncated or corrupted on the way.
+    let etag = response
+        .headers()
+        .get(ETAG)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.trim_matches('"').to_owned());
+    match etag {
+        Some(etag) if !etag.contains('-') => {
+            let md5 = format!("{:x}", md5::compute(body));
+            if etag != md5 {
+                warn!(url, etag, md5, "Upload etag mismatch");
+                return Err(RunnerError::Content(format!(
+                    "etag {etag} doesn't match md5 {md5}"
+                )));
+            }
+        },
+        _ => debug!(url, "Upload returned no comparable etag"),
+    }
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn local_storage_returns_the_public_url() {
+        let dir = std::env::temp_dir()
+            .join(format!("fia-docs-api-storage-{}", std::process::id()));
+        let storage = StorageConfig {
+            backend: StorageBackend::Local(dir.clone()),
+            public_url: "https://mirror.example".to_owned(),
+            series_public_urls: HashMap::from([(
+                Series::f2,
+                "https://f2.example".to_owned(),
+            )]),
+            content_addressed_images: false,
+            prefix: "staging/".to_owned(),
+        };
+        let client = reqwest::Client::new();
+
+        let f1 = open(&client, &storage, Series::f1);
+        let url = f1.put("mirror/doc.pdf", b"pdf", "application/pdf").await;
+        assert_eq!(
+            url.unwrap(),
+            "https://mirror.example/staging/mirror/doc.pdf"
+        );
+        let path = dir.join("staging/mirror/doc.pdf");
+        assert_eq!(std::fs::read(&path).unwrap(), b"pdf");
+        let f2 = open(&client, &storage, Series::f2);
+        let url = f2.put("mirror/doc.pdf", b"pdf", "application/pdf").await;
+        assert_eq!(url.unwrap(), "https://f2.example/staging/mirror/doc.pdf");
+
+        f1.delete("mirror/doc.pdf").await.unwrap();
+        assert!(!path.exists());
+        f1.delete("mirror/doc.pdf").await.unwrap();
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
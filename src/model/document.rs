@@ -1,25 +1,400 @@
+use std::str::FromStr;
+
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-use super::series::Series;
+use super::{series::Series, source::Source};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Image {
-    pub id: Option<u64>,
+    pub id: Option<i64>,
     pub url: String,
     pub page: u8,
-    pub document: u64,
+    pub document: i64,
     pub created: DateTime<Utc>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Document {
-    pub id: Option<u64>,
-    pub event: u64,
+    pub id: Option<i64>,
+    pub event: i64,
     pub title: String,
     pub series: Series,
+    pub source: Source,
     pub created: DateTime<Utc>,
+    pub published: DateTime<Utc>,
+    /// the document as published, kept verbatim for provenance.
     pub url: String,
+    /// our own copy, stays up when the source pulls the document.
     pub mirror: String,
     pub notified: bool,
+    pub hash: Option<String>,
+    /// set once the FIA pulled the document from their site.
+    pub removed: Option<DateTime<Utc>>,
+    /// number of page images, none if the document wasn't converted.
+    pub page_count: Option<i32>,
+    pub doc_type: DocumentType,
+    pub media_type: MediaType,
+    /// the FIA's running number within the event, if the title has one.
+    pub doc_number: Option<i32>,
+    /// the file name the FIA served the document under, if they sent one.
+    pub original_filename: Option<String>,
+    /// the earlier document a revision replaces, a best guess.
+    pub supersedes: Option<i64>,
+}
+
+// words the FIA marks a replacement with.
+const REVISION_MARKERS: [&str; 4] =
+    ["revised", "corrected", "amended", "updated"];
+
+/// Reads the number off a title like "Doc 34 - Decision - Car 44".
+pub fn doc_number(title: &str) -> Option<i32> {
+    let title = title.trim_start();
+    let prefix = title.get(..3)?;
+    if !prefix.eq_ignore_ascii_case("doc") {
+        return None;
+    }
+    let rest = title[3..].trim_start_matches(['.', ' ']);
+    let digits = rest
+        .find(|c: char| !c.is_ascii_digit())
+        .map_or(rest, |end| &rest[..end]);
+    return digits.parse().ok();
+}
+
+/// Whether the title marks a replacement, like "Decision - Car 44 (Revised)".
+pub fn is_revision(title: &str) -> bool {
+    return title_words(title).any(|w| REVISION_MARKERS.contains(&w.as_str()));
+}
+
+/// What a document is about, without its number and revision markers. A
+/// revision shares it with the document it replaces.
+pub fn revision_subject(title: &str) -> String {
+    let mut words: Vec<String> = title_words(title).collect();
+    if words.first().is_some_and(|w| w == "doc")
+        && words.get(1).is_some_and(|w| w.bytes().all(|b| b.is_ascii_digit()))
+    {
+        words.drain(..2);
+    }
+    words.retain(|w| !REVISION_MARKERS.contains(&w.as_str()));
+    return words.join(" ");
+}
+
+/// Whether the revision titled `revised` replaces `original`, by their
+/// subject, or by their type and cars when the wording changed.
+pub fn revises(
+    revised: &str,
+    original: &str,
+) -> bool {
+    if revision_subject(revised) == revision_subject(original) {
+        return true;
+    }
+    let cars = car_numbers(revised);
+    return !cars.is_empty()
+        && car_numbers(original) == cars
+        && DocumentType::classify(original) == DocumentType::classify(revised);
+}
+
+/// The car numbers a title names, e.g. `["44"]` for "Car 44 - ...".
+pub fn car_numbers(title: &str) -> Vec<String> {
+    let words: Vec<String> = title_words(title).collect();
+    return words
+        .windows(2)
+        .filter(|pair| pair[0] == "car" || pair[0] == "cars")
+        .map(|pair| pair[1].clone())
+        .collect();
+}
+
+fn title_words(title: &str) -> impl Iterator<Item = String> + '_ {
+    return title
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase());
+}
+
+/// What the FIA actually published, almost always a pdf.
+#[derive(Serialize, Deserialize, Clone, Copy, Eq, PartialEq, Debug)]
+pub enum MediaType {
+    #[serde(rename = "application/pdf")]
+    Pdf,
+    /// scans posted as is.
+    #[serde(rename = "image/png")]
+    Png,
+    #[serde(rename = "image/jpeg")]
+    Jpeg,
+}
+
+impl MediaType {
+    /// Detects the type from the leading bytes of a file, servers don't
+    /// reliably send a content type.
+    pub fn sniff(body: &[u8]) -> Option<Self> {
+        if body.starts_with(b"%PDF-") {
+            return Some(Self::Pdf);
+        }
+        if body.starts_with(b"\x89PNG\r\n\x1a\n") {
+            return Some(Self::Png);
+        }
+        if body.starts_with(&[0xff, 0xd8, 0xff]) {
+            return Some(Self::Jpeg);
+        }
+        return None;
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Pdf => "pdf",
+            Self::Png => "png",
+            Self::Jpeg => "jpg",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Pdf => "application/pdf",
+            Self::Png => "image/png",
+            Self::Jpeg => "image/jpeg",
+        }
+    }
+}
+
+impl From<MediaType> for String {
+    fn from(value: MediaType) -> Self {
+        return value.content_type().to_owned();
+    }
+}
+
+impl FromStr for MediaType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        return match s {
+            "application/pdf" => Ok(Self::Pdf),
+            "image/png" => Ok(Self::Png),
+            "image/jpeg" => Ok(Self::Jpeg),
+            _ => Err(format!("unknown media type \"{s}\"")),
+        };
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Eq, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum DocumentType {
+    Decision,
+    Summons,
+    Offence,
+    Infringement,
+    PitLaneStart,
+    StartingGrid,
+    Classification,
+    /// track maps and other diagrams.
+    Map,
+    Other,
+}
+
+impl DocumentType {
+    /// Guesses the type from an FIA title like "Decision - Car 44 - ...".
+    pub fn classify(title: &str) -> Self {
+        let words: Vec<String> = title_words(title).collect();
+        // whole words only, "map" shouldn't match "bitmap". plurals count.
+        let matches = |pattern: &str| {
+            let pattern: Vec<&str> = pattern.split(' ').collect();
+            return words.windows(pattern.len()).any(|window| {
+                window.iter().zip(&pattern).all(|(word, pattern)| {
+                    word == *pattern || word.strip_suffix('s') == Some(*pattern)
+                })
+            });
+        };
+        // checked in order, a decision usually names the offence as well.
+        let patterns = [
+            ("decision", Self::Decision),
+            ("summons", Self::Summons),
+            ("offence", Self::Offence),
+            ("infringement", Self::Infringement),
+            ("pit lane start", Self::PitLaneStart),
+            ("starting grid", Self::StartingGrid),
+            ("classification", Self::Classification),
+            ("map", Self::Map),
+            ("diagram", Self::Map),
+        ];
+        return patterns
+            .into_iter()
+            .find(|(pattern, _)| matches(pattern))
+            .map_or(Self::Other, |(_, kind)| kind);
+    }
+
+    /// Whether the document is mostly thin lines, which needs a lossless
+    /// image format to stay legible.
+    pub fn is_line_art(&self) -> bool {
+        return matches!(self, Self::Map);
+    }
+}
+
+impl From<DocumentType> for String {
+    fn from(value: DocumentType) -> Self {
+        let str = match value {
+            DocumentType::Decision => "decision",
+            DocumentType::Summons => "summons",
+            DocumentType::Offence => "offence",
+            DocumentType::Infringement => "infringement",
+            DocumentType::PitLaneStart => "pit_lane_start",
+            DocumentType::StartingGrid => "starting_grid",
+            DocumentType::Classification => "classification",
+            DocumentType::Map => "map",
+            DocumentType::Other => "other",
+        };
+        return str.to_owned();
+    }
+}
+
+impl FromStr for DocumentType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        return match s {
+            "decision" => Ok(Self::Decision),
+            "summons" => Ok(Self::Summons),
+            "offence" => Ok(Self::Offence),
+            "infringement" => Ok(Self::Infringement),
+            "pit_lane_start" => Ok(Self::PitLaneStart),
+            "starting_grid" => Ok(Self::StartingGrid),
+            "classification" => Ok(Self::Classification),
+            "map" => Ok(Self::Map),
+            "other" => Ok(Self::Other),
+            _ => Err(format!("unknown document type \"{s}\"")),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_common_titles() {
+        let titles = [
+            ("Doc 12 - Decision - Car 44 - Impeding", DocumentType::Decision),
+            ("Summons - Car 1 - Alleged unsafe release", DocumentType::Summons),
+            (
+                "Offence - Car 16 - Leaving the track and gaining an advantage",
+                DocumentType::Offence,
+            ),
+            (
+                "Doc 33 - Infringement - Car 31 - Track limits",
+                DocumentType::Infringement,
+            ),
+            ("Pit Lane Start - Car 2", DocumentType::PitLaneStart),
+            ("Doc 40 - Final Starting Grid", DocumentType::StartingGrid),
+            (
+                "Doc 48 - Final Race Classification",
+                DocumentType::Classification,
+            ),
+            ("Doc 3 - Circuit Map", DocumentType::Map),
+            ("Pit Lane Entry and Exit Diagram", DocumentType::Map),
+            ("Doc 1 - Event Notes", DocumentType::Other),
+            ("Team Managers' Meeting Agenda", DocumentType::Other),
+        ];
+        for (title, doc_type) in titles {
+            assert_eq!(DocumentType::classify(title), doc_type, "{title}");
+        }
+    }
+
+    #[test]
+    fn document_types_round_trip_through_strings() {
+        let types = [
+            DocumentType::Decision,
+            DocumentType::Summons,
+            DocumentType::Offence,
+            DocumentType::Infringement,
+            DocumentType::PitLaneStart,
+            DocumentType::StartingGrid,
+            DocumentType::Classification,
+            DocumentType::Map,
+            DocumentType::Other,
+        ];
+        for doc_type in types {
+            let str: String = doc_type.into();
+            assert_eq!(str.parse(), Ok(doc_type));
+        }
+        assert!("bitmap".parse::<DocumentType>().is_err());
+    }
+
+    #[test]
+    fn classify_ignores_case() {
+        assert_eq!(
+            DocumentType::classify("DECISION - CAR 4"),
+            DocumentType::Decision
+        );
+        assert_eq!(
+            DocumentType::classify("provisional classification"),
+            DocumentType::Classification
+        );
+    }
+
+    #[test]
+    fn reads_the_doc_number() {
+        assert_eq!(doc_number("Doc 34 - Decision - Car 44"), Some(34));
+        assert_eq!(doc_number("  DOC 7 - Event Notes"), Some(7));
+        assert_eq!(doc_number("Doc. 5 - Circuit Map"), Some(5));
+        assert_eq!(doc_number("doc12 - Summons"), Some(12));
+    }
+
+    #[test]
+    fn titles_without_a_doc_number() {
+        assert_eq!(doc_number("Decision - Car 44 - Impeding"), None);
+        assert_eq!(doc_number("Document 3 - Event Notes"), None);
+        assert_eq!(doc_number("Doc - Final Starting Grid"), None);
+        assert_eq!(doc_number("Do"), None);
+        assert_eq!(doc_number(""), None);
+    }
+
+    #[test]
+    fn links_a_revised_decision_to_the_original() {
+        let original = "Doc 34 - Decision - Car 44 - Impeding";
+        let revised = "Doc 40 - Decision - Car 44 - Impeding (Revised)";
+        assert!(!is_revision(original));
+        assert!(is_revision(revised));
+        assert_eq!(revision_subject(original), "decision car 44 impeding");
+        assert_eq!(revision_subject(revised), revision_subject(original));
+        assert_eq!(car_numbers(revised), ["44"]);
+        assert!(revises(revised, original));
+    }
+
+    #[test]
+    fn links_a_reworded_revision_by_its_cars() {
+        let original = "Doc 52 - Infringement - Car 31 - Track limits";
+        let corrected =
+            "Doc 55 - Corrected Infringement - Car 31 - Track limits at Turn 4";
+        assert!(is_revision(corrected));
+        assert!(revises(corrected, original));
+        // same type, another car.
+        assert!(!revises(
+            corrected,
+            "Doc 53 - Infringement - Car 10 - Track limits"
+        ));
+        // same car, another type.
+        assert!(!revises(corrected, "Doc 50 - Summons - Car 31 - Impeding"));
+    }
+
+    #[test]
+    fn classify_matches_whole_words() {
+        let titles = [
+            ("Doc 7 - Timing Bitmap Overlay", DocumentType::Other),
+            ("Doc 8 - Remapping of Turn 4 Kerbs", DocumentType::Other),
+            ("Doc 9 - Circuit Maps", DocumentType::Map),
+            ("Stewards Decisions - Car 3", DocumentType::Decision),
+            ("Doc 10 - Pit-Lane Start - Car 2", DocumentType::PitLaneStart),
+        ];
+        for (title, doc_type) in titles {
+            assert_eq!(DocumentType::classify(title), doc_type, "{title}");
+        }
+    }
+
+    #[test]
+    fn decisions_win_over_the_offence_they_name() {
+        assert_eq!(
+            DocumentType::classify(
+                "Decision - Car 22 - Infringement of Art. 33.4"
+            ),
+            DocumentType::Decision
+        );
+    }
 }
@@ -1,3 +1,4 @@
 pub mod document;
 pub mod event;
 pub mod series;
+pub mod source;
@@ -1,5 +1,9 @@
 use serde::{Deserialize, Serialize};
 
+pub const FIA_URL: &str = "https://www.fia.com";
+/// F1 Academy publishes on Sportity instead of fia.com.
+pub const SPORTITY_URL: &str = "https://webapp.sportity.com";
+
 #[derive(
     Serialize, Deserialize, Clone, Copy, Eq, PartialEq, Debug, Hash, sqlx::Type,
 )]
@@ -11,6 +15,64 @@ pub enum Series {
     f2,
     #[serde(rename = "f3", alias = "F3")]
     f3,
+    #[serde(rename = "wec", alias = "WEC")]
+    wec,
+    #[serde(rename = "wrc", alias = "WRC")]
+    wrc,
+    #[serde(rename = "fe", alias = "FE")]
+    fe,
+    #[serde(rename = "f1a", alias = "F1A")]
+    f1a,
+}
+
+impl Series {
+    /// Every series, adding one only needs a variant here and its url.
+    pub fn all() -> &'static [Series] {
+        return &[
+            Self::f1,
+            Self::f2,
+            Self::f3,
+            Self::wec,
+            Self::wrc,
+            Self::fe,
+            Self::f1a,
+        ];
+    }
+
+    /// Url of the series' documents for a season, `season_id` is the FIA's
+    /// internal id for `year`.
+    pub fn docs_url(
+        &self,
+        year: i32,
+        season_id: u32,
+    ) -> String {
+        let base = match self {
+            Self::f1a => SPORTITY_URL,
+            _ => FIA_URL,
+        };
+        return format!("{base}{}", self.docs_path(year, season_id));
+    }
+
+    /// Path of `docs_url` on fia.com, or on Sportity for F1 Academy.
+    pub fn docs_path(
+        &self,
+        year: i32,
+        season_id: u32,
+    ) -> String {
+        let season = format!("season-{year}-{season_id}");
+        return match self {
+            Self::f1 => format!("/documents/championships/fia-formula-one-world-championship-14/season/{season}"),
+            Self::f2 => format!("/documents/season/{season}/championships/formula-2-championship-44"),
+            Self::f3 => format!("/documents/season/{season}/championships/fia-formula-3-championship-1012"),
+            Self::wec => format!("/documents/season/{season}/championships/fia-world-endurance-championship-229"),
+            // rallies are grouped like race weekends, one event per rally.
+            Self::wrc => format!("/documents/season/{season}/championships/fia-world-rally-championship-7"),
+            // double-headers are a single event, the documents name the race.
+            Self::fe => format!("/documents/season/{season}/championships/fia-formula-e-world-championship-1001"),
+            // a channel per season, the FIA's season id doesn't apply.
+            Self::f1a => format!("/channel/F1A{year}"),
+        };
+    }
 }
 
 impl From<Series> for String {
@@ -19,17 +81,42 @@ impl From<Series> for String {
             Series::f1 => "f1".to_owned(),
             Series::f2 => "f2".to_owned(),
             Series::f3 => "f3".to_owned(),
+            Series::wec => "wec".to_owned(),
+            Series::wrc => "wrc".to_owned(),
+            Series::fe => "fe".to_owned(),
+            Series::f1a => "f1a".to_owned(),
         }
     }
 }
 
-impl From<String> for Series {
-    fn from(value: String) -> Self {
-        return match value.as_str() {
-            "f1" | "F1" => Series::f1,
-            "f2" | "F2" => Series::f2,
-            "f3" | "F3" => Series::f3,
-            _ => Series::f1,
+impl TryFrom<String> for Series {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        return value.parse();
+    }
+}
+
+impl std::str::FromStr for Series {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        return match s.trim().to_lowercase().as_str() {
+            "f1" => Ok(Series::f1),
+            "f2" => Ok(Series::f2),
+            "f3" => Ok(Series::f3),
+            "wec" => Ok(Series::wec),
+            "wrc" => Ok(Series::wrc),
+            "fe" => Ok(Series::fe),
+            "f1a" => Ok(Series::f1a),
+            _ => Err(format!(
+                "unknown series \"{s}\", expected one of {}",
+                Series::all()
+                    .iter()
+                    .map(|series| series.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
         };
     }
 }
@@ -43,7 +130,65 @@ impl std::fmt::Display for Series {
             Self::f1 => "f1",
             Self::f2 => "f2",
             Self::f3 => "f3",
+            Self::wec => "wec",
+            Self::wrc => "wrc",
+            Self::fe => "fe",
+            Self::f1a => "f1a",
         };
         f.write_str(str)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_strings() {
+        for series in Series::all() {
+            let name: String = (*series).into();
+            assert_eq!(name, series.to_string());
+            assert_eq!(name.parse::<Series>(), Ok(*series));
+            assert_eq!(Series::try_from(name), Ok(*series));
+        }
+    }
+
+    #[test]
+    fn parses_case_and_whitespace_insensitively() {
+        assert_eq!(" F1 ".parse::<Series>(), Ok(Series::f1));
+        assert_eq!("WEC".parse::<Series>(), Ok(Series::wec));
+    }
+
+    #[test]
+    fn rejects_unknown_series() {
+        let why = "f4".parse::<Series>().unwrap_err();
+        assert!(why.starts_with("unknown series \"f4\""), "{why}");
+        assert!(why.contains("f1, f2, f3"), "{why}");
+        assert!(Series::try_from(String::new()).is_err());
+    }
+
+    #[test]
+    fn docs_url_follows_the_season_path() {
+        assert_eq!(
+            Series::f1.docs_url(2025, 2071),
+            "https://www.fia.com/documents/championships/fia-formula-one-world-championship-14/season/season-2025-2071"
+        );
+        assert_eq!(
+            Series::f2.docs_url(2024, 2043),
+            "https://www.fia.com/documents/season/season-2024-2043/championships/formula-2-championship-44"
+        );
+        assert_eq!(
+            Series::f1a.docs_url(2025, 2071),
+            "https://webapp.sportity.com/channel/F1A2025"
+        );
+    }
+
+    #[test]
+    fn docs_url_has_the_season_of_every_fia_series() {
+        for series in Series::all().iter().filter(|s| **s != Series::f1a) {
+            let url = series.docs_url(2024, 2043);
+            assert!(url.starts_with("https://www.fia.com/documents/"), "{url}");
+            assert!(url.contains("/season-2024-2043"), "{url}");
+        }
+    }
+}
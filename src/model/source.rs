@@ -0,0 +1,64 @@
+use serde::{Deserialize, Serialize};
+
+use super::series::Series;
+
+/// Where a document was originally published.
+#[derive(Serialize, Deserialize, Clone, Copy, Eq, PartialEq, Debug)]
+pub enum Source {
+    #[serde(rename = "fia.com")]
+    Fia,
+    #[serde(rename = "sportity")]
+    Sportity,
+}
+
+impl From<Source> for String {
+    fn from(value: Source) -> Self {
+        match value {
+            Source::Fia => "fia.com".to_owned(),
+            Source::Sportity => "sportity".to_owned(),
+        }
+    }
+}
+
+impl std::str::FromStr for Source {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        return match s {
+            "fia.com" => Ok(Source::Fia),
+            "sportity" => Ok(Source::Sportity),
+            _ => Err(format!("unknown source \"{s}\"")),
+        };
+    }
+}
+
+impl From<Series> for Source {
+    fn from(value: Series) -> Self {
+        return match value {
+            Series::f1a => Source::Sportity,
+            _ => Source::Fia,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn f1_academy_comes_from_sportity() {
+        assert_eq!(Source::from(Series::f1a), Source::Sportity);
+        for series in Series::all().iter().filter(|s| **s != Series::f1a) {
+            assert_eq!(Source::from(*series), Source::Fia, "{series}");
+        }
+    }
+
+    #[test]
+    fn round_trips_through_strings() {
+        for source in [Source::Fia, Source::Sportity] {
+            let name: String = source.into();
+            assert_eq!(name.parse::<Source>(), Ok(source));
+        }
+        assert!("sportity.com".parse::<Source>().is_err());
+    }
+}
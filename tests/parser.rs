@@ -0,0 +1,157 @@
+use std::{num::NonZeroI16, path::Path};
+
+use fia_docs_api::{middleware::parser::parse_date, parse_season, Season};
+
+fn fixture(name: &str) -> Season {
+    let path =
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name);
+    let html = std::fs::read(&path).unwrap();
+    return parse_season(&html, NonZeroI16::new(2024).unwrap()).unwrap();
+}
+
+fn titles(season: &Season) -> Vec<&str> {
+    return season
+        .events
+        .iter()
+        .map(|event| event.title.as_deref().unwrap())
+        .collect();
+}
+
+#[test]
+fn f1_season() {
+    let season = fixture("f1_2024.html");
+    assert_eq!(titles(&season), ["Bahrain Grand Prix", "Pre-Season Testing"]);
+    assert_eq!(season.next_page, None);
+
+    let bahrain = &season.events[0];
+    assert_eq!(bahrain.season, NonZeroI16::new(2024));
+    assert_eq!(bahrain.documents.len(), 3);
+    assert_eq!(bahrain.incomplete, 0);
+    let doc = &bahrain.documents[0];
+    assert_eq!(doc.title, "Doc 48 - Final Race Classification");
+    assert_eq!(
+        doc.url,
+        "https://www.fia.com/sites/default/files/decision-document/2024%20Bahrain%20Grand%20Prix%20-%20Final%20Race%20Classification.pdf"
+    );
+    assert_eq!(doc.date, "02.03.24 19:27");
+    assert_eq!(
+        parse_date(&doc.date).unwrap().to_rfc3339(),
+        "2024-03-02T18:27:00+00:00"
+    );
+    assert_eq!(
+        bahrain.documents[2].title,
+        "Doc 12 - Summons - Car 44 - Impeding"
+    );
+
+    let testing = &season.events[1];
+    assert_eq!(testing.documents.len(), 2);
+    assert_eq!(testing.documents[1].title, "Doc 1 - Event Notes");
+    assert_eq!(testing.documents[1].date, "19.02.24 17:45");
+}
+
+#[test]
+fn f2_season() {
+    let season = fixture("f2_2024.html");
+    assert_eq!(
+        titles(&season),
+        ["Saudi Arabian Grand Prix", "Bahrain Grand Prix"]
+    );
+    assert_eq!(season.events[0].documents.len(), 2);
+    assert_eq!(season.events[1].documents.len(), 1);
+    let doc = &season.events[0].documents[1];
+    assert_eq!(doc.title, "Doc 22 - Decision - Car 1 - Causing a collision");
+    assert_eq!(
+        doc.url,
+        "https://www.fia.com/sites/default/files/decision-document/2024%20Saudi%20Arabian%20Grand%20Prix%20-%20F2%20Decision%20-%20Car%201%20-%20Causing%20a%20collision.pdf"
+    );
+    assert_eq!(doc.date, "08.03.24 20:40");
+}
+
+#[test]
+fn f3_season() {
+    let season = fixture("f3_2024.html");
+    assert_eq!(titles(&season), ["Australian Grand Prix"]);
+    let docs = &season.events[0].documents;
+    assert_eq!(docs.len(), 2);
+    assert_eq!(docs[1].title, "Doc 24 - Pit Lane Start");
+    assert_eq!(
+        docs[1].url,
+        "https://www.fia.com/sites/default/files/decision-document/2024%20Australian%20Grand%20Prix%20-%20F3%20Pit%20Lane%20Start.pdf"
+    );
+    assert_eq!(docs[1].date, "23.03.24 12:48");
+}
+
+#[test]
+fn wec_season() {
+    // endurance events span several days, still one event per race.
+    let season = fixture("wec_2024.html");
+    assert_eq!(titles(&season), ["Qatar 1812 KM"]);
+    let docs = &season.events[0].documents;
+    assert_eq!(docs.len(), 3);
+    assert_eq!(docs[0].title, "Doc 31 - Final Classification");
+    assert_eq!(docs[0].date, "02.03.24 23:58");
+    assert_eq!(docs[2].date, "28.02.24 09:15");
+    assert_eq!(
+        docs[2].url,
+        "https://www.fia.com/sites/default/files/decision-document/2024%20Qatar%201812%20KM%20-%20Event%20Notes.pdf"
+    );
+}
+
+#[test]
+fn wrc_season() {
+    let season = fixture("wrc_2024.html");
+    assert_eq!(titles(&season), ["Rallye Monte-Carlo"]);
+    let docs = &season.events[0].documents;
+    assert_eq!(docs.len(), 2);
+    assert_eq!(docs[1].title, "Doc 9 - Stewards Decision - Car 17");
+    assert_eq!(
+        docs[1].url,
+        "https://www.fia.com/sites/default/files/decision-document/2024%20Rallye%20Monte-Carlo%20-%20Stewards%20Decision%20-%20Car%2017.pdf"
+    );
+    assert_eq!(docs[1].date, "26.01.24 21:40");
+}
+
+#[test]
+fn fe_season() {
+    // both races of a double-header are one event.
+    let season = fixture("fe_2024.html");
+    assert_eq!(titles(&season), ["Diriyah E-Prix", "Mexico City E-Prix"]);
+    let docs = &season.events[0].documents;
+    assert_eq!(docs.len(), 2);
+    assert_eq!(docs[0].title, "Doc 41 - Race 2 - Final Classification");
+    assert_eq!(docs[1].title, "Doc 20 - Race 1 - Final Classification");
+    assert_eq!(docs[1].date, "26.01.24 22:05");
+    assert_eq!(season.events[1].documents.len(), 1);
+}
+
+#[test]
+fn wrapped_titles_are_normalized() {
+    let season = fixture("wrapped_titles.html");
+    assert_eq!(titles(&season), ["Emilia Romagna Grand Prix"]);
+    let docs = &season.events[0].documents;
+    assert_eq!(docs.len(), 2);
+    assert_eq!(docs[0].title, "Doc 27 - Decision - Car 81 - Practice start");
+    // split at the entity and the nested span, joined back together.
+    assert_eq!(docs[1].title, "Doc 26 - Stewards & Race Director Notes");
+    assert_eq!(
+        docs[1].url,
+        "https://www.fia.com/sites/default/files/decision-document/2024%20Emilia%20Romagna%20Grand%20Prix%20-%20Stewards%20&%20Race%20Director%20Notes.pdf"
+    );
+}
+
+#[test]
+fn documents_across_midnight_stay_in_one_event() {
+    let season = fixture("cross_midnight.html");
+    assert_eq!(
+        titles(&season),
+        ["Las Vegas Grand Prix", "Sao Paulo Grand Prix"]
+    );
+    let docs = &season.events[0].documents;
+    assert_eq!(docs.len(), 4);
+    let days: Vec<String> = docs
+        .iter()
+        .map(|doc| parse_date(&doc.date).unwrap().format("%F").to_string())
+        .collect();
+    assert_eq!(days, ["2024-11-24", "2024-11-24", "2024-11-23", "2024-11-23"]);
+    assert_eq!(season.events[1].documents.len(), 1);
+}